@@ -0,0 +1,131 @@
+//! repo.rs
+//!
+//! Benchmarks of parsing, loading, interval containment and resolution
+//! against a synthetic repository. The size of the repository may be set with
+//! VERSIONITIS_BENCH_PACKAGES and VERSIONITIS_BENCH_VERSIONS, eg to match a
+//! 40k manifest studio repo:
+//!
+//! ```text
+//! VERSIONITIS_BENCH_PACKAGES=4000 VERSIONITIS_BENCH_VERSIONS=10 cargo bench
+//! ```
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::path::PathBuf;
+use versionitis::{
+    manifest::{Manifest, Phase},
+    manifest_repo::{ManifestArena, ManifestRepo},
+    package::Package,
+    synthetic::{SyntheticRepo, MANIFEST_DIR},
+    version_number::VersionNumber,
+    version_number_interval::VersionNumberInterval,
+};
+
+// read a size from the environment, falling back to a default
+fn size(var: &str, default: usize) -> usize {
+    std::env::var(var).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn sizes() -> (usize, usize) {
+    (size("VERSIONITIS_BENCH_PACKAGES", 200), size("VERSIONITIS_BENCH_VERSIONS", 10))
+}
+
+// the density and seed of the synthetic repo, which name its scratch directory
+const DENSITY: f64 = 3.0;
+const SEED: u64 = 1;
+
+fn config() -> SyntheticRepo {
+    let (packages, versions) = sizes();
+    SyntheticRepo::new(packages, versions).density(DENSITY).seed(SEED)
+}
+
+// write the synthetic repo to a scratch directory named for its
+// configuration, which later runs reuse, as writing a large repo takes a
+// while. The repo is written alongside and renamed into place once complete,
+// so that an interrupted write is never reused.
+fn scratch() -> PathBuf {
+    let (packages, versions) = sizes();
+    let name = format!("versionitis-bench-{}x{}-d{}-s{}", packages, versions, DENSITY, SEED);
+    let dir = std::env::temp_dir().join(&name);
+    if !dir.is_dir() {
+        let partial = std::env::temp_dir().join(format!("{}.partial-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&partial);
+        config().write(&partial).unwrap();
+        // another run may have finished first, in which case its repo is used
+        if fs::rename(&partial, &dir).is_err() {
+            fs::remove_dir_all(&partial).unwrap();
+        }
+    }
+    dir
+}
+
+fn parsing(c: &mut Criterion) {
+    let (_, manifests) = config().generate();
+    let yaml = manifests.iter().map(|m| serde_yaml::to_string(m).unwrap()).collect::<Vec<_>>();
+    let intervals = manifests
+        .iter()
+        .flat_map(|m| m.dependencies().values().map(|i| i.to_string()))
+        .collect::<Vec<_>>();
+    let specs = manifests.iter().map(|m| m.package().spec()).collect::<Vec<_>>();
+
+    c.bench_function("parse packages", |b| {
+        b.iter(|| specs.iter().map(|s| Package::from_str(black_box(s)).unwrap()).collect::<Vec<_>>())
+    });
+    c.bench_function("parse intervals", |b| {
+        b.iter(|| intervals.iter().map(|s| VersionNumberInterval::from_str(black_box(s)).unwrap()).collect::<Vec<_>>())
+    });
+    c.bench_function("parse manifests", |b| {
+        b.iter(|| yaml.iter().map(|s| serde_yaml::from_str::<Manifest>(black_box(s)).unwrap()).collect::<Vec<_>>())
+    });
+}
+
+fn loading(c: &mut Criterion) {
+    let dir = scratch().join(MANIFEST_DIR);
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+    group.bench_function("from_disk", |b| {
+        b.iter(|| {
+            let arena = ManifestArena::new();
+            ManifestRepo::from_disk(black_box(&dir), &arena).unwrap().len()
+        })
+    });
+    group.finish();
+}
+
+fn containment(c: &mut Criterion) {
+    let (_, manifests) = config().generate();
+    let intervals = manifests
+        .iter()
+        .flat_map(|m| m.dependencies().values().cloned())
+        .collect::<Vec<_>>();
+    let versions = (0..10).map(SyntheticRepo::version_number).collect::<Vec<VersionNumber>>();
+    c.bench_function("interval contains", |b| {
+        b.iter(|| {
+            intervals
+                .iter()
+                .map(|interval| versions.iter().filter(|v| interval.contains(black_box(v))).count())
+                .sum::<usize>()
+        })
+    });
+}
+
+fn resolution(c: &mut Criterion) {
+    let config = config();
+    let (_, manifests) = config.generate();
+    let roots = manifests.iter().rev().take(10).map(|m| *m.package()).collect::<Vec<_>>();
+    let arena = ManifestArena::new();
+    let mut repo = ManifestRepo::new(&arena);
+    for manifest in manifests {
+        repo.add(manifest);
+    }
+    c.bench_function("closure", |b| {
+        b.iter(|| {
+            roots
+                .iter()
+                .map(|root| repo.closure(black_box(root), Phase::Run).map(|c| c.len()).unwrap_or(0))
+                .sum::<usize>()
+        })
+    });
+}
+
+criterion_group!(benches, parsing, loading, containment, resolution);
+criterion_main!(benches);
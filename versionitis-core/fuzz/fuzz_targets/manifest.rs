@@ -0,0 +1,17 @@
+//! manifest.rs
+//!
+//! Fuzz Manifest deserialization, both directly through serde and through
+//! Format::parse_manifest, which upgrades older schemas, in every format.
+//!
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use versionitis::{format::Format, manifest::Manifest};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = serde_yaml::from_str::<Manifest>(input);
+        for format in Format::ALL.iter() {
+            let _ = format.parse_manifest(input);
+        }
+    }
+});
@@ -0,0 +1,18 @@
+//! package_version_parser.rs
+//!
+//! Fuzz PackageVersionParser::parse and parse_qualified, checking that any
+//! package which parses round trips through its spec.
+//!
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use versionitis::{package::Package, package_version_parser::PackageVersionParser};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = PackageVersionParser::parse(input);
+        let _ = PackageVersionParser::parse_qualified(input);
+        if let Ok(package) = Package::from_str(input) {
+            assert_eq!(Package::from_str(&package.spec()), Ok(package));
+        }
+    }
+});
@@ -0,0 +1,16 @@
+//! vernum_interval_parser.rs
+//!
+//! Fuzz VerNumIntervalParser::parse, checking that any interval which parses
+//! round trips through to_range.
+//!
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use versionitis::vernum_interval_parser::VerNumIntervalParser;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        if let Ok(interval) = VerNumIntervalParser::parse(input) {
+            assert_eq!(VerNumIntervalParser::parse(&interval.to_range()), Ok(interval));
+        }
+    }
+});
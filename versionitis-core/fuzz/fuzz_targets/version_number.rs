@@ -0,0 +1,16 @@
+//! version_number.rs
+//!
+//! Fuzz VersionNumber::from_str, checking that any version number which
+//! parses round trips through its display form.
+//!
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use versionitis::version_number::VersionNumber;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        if let Ok(version) = VersionNumber::from_str(input) {
+            assert_eq!(VersionNumber::from_str(&version.to_string()), Ok(version));
+        }
+    }
+});
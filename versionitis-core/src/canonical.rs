@@ -0,0 +1,96 @@
+//! canonical.rs
+//!
+//! Stable, diff friendly serialization. Maps are written with their keys
+//! sorted, and manifests may be rewritten in a canonical format.
+//!
+use crate::{errors::VersionitisError, manifest::Manifest};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::io::Write;
+
+/// Serialize a HashMap with its keys sorted. Intended for use with
+/// `#[serde(serialize_with = "...")]`.
+pub fn serialize_sorted<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Serialize + Ord + Hash,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<&K, &V>>().serialize(serializer)
+}
+
+/// Serialize a value as yaml in the canonical format: a document marker
+/// followed by the value, with map keys sorted and a single trailing newline.
+pub fn to_canonical_string<T: Serialize>(value: &T) -> Result<String, VersionitisError> {
+    let yaml = serde_yaml::to_string(value)?;
+    let body = yaml.trim_start_matches("---").trim_start_matches('\n').trim_end();
+    Ok(format!("---\n{}\n", body))
+}
+
+/// Write a value as yaml in the canonical format
+pub fn write_canonical<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<(), VersionitisError> {
+    writer.write_all(to_canonical_string(value)?.as_bytes())?;
+    Ok(())
+}
+
+/// Rewrite manifest yaml in the canonical format, normalizing spacing, key
+/// order and the spelling of intervals (eg `0.1.0 <  1.0.0` becomes
+/// `0.1.0<1.0.0`). Manifests are upgraded to the current schema version.
+/// Comments are not preserved.
+///
+/// # Example
+///
+/// ```
+/// use versionitis::canonical::canonicalize_manifest;
+/// let yaml = canonicalize_manifest("name: foo-1.0.0\ndependencies: { bar: ' 0.1.0 <  1.0.0' }").unwrap();
+/// assert_eq!(yaml, "---\nschema_version: 2\nname: foo-1.0.0\ndependencies:\n  bar: 0.1.0<1.0.0\n");
+/// ```
+pub fn canonicalize_manifest(yaml: &str) -> Result<String, VersionitisError> {
+    let manifest: Manifest = crate::schema::load_manifest(yaml)?;
+    to_canonical_string(&manifest)
+}
+
+/// Test whether manifest yaml is already in the canonical format. Suitable
+/// for use in a pre-commit hook.
+pub fn is_canonical_manifest(yaml: &str) -> Result<bool, VersionitisError> {
+    Ok(canonicalize_manifest(yaml)? == yaml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interval_map::IntervalMap, package_repo::PackageRepo, traits::TrackPackages};
+    use crate::version_number_interval::VersionNumberInterval;
+
+    #[test]
+    fn interval_maps_serialize_sorted() {
+        let mut map = IntervalMap::new();
+        for name in &["zed", "abc", "mno", "bar"] {
+            map.insert(name.to_string(), VersionNumberInterval::from_str("1.0.0").unwrap());
+        }
+        let yaml = to_canonical_string(&map).unwrap();
+        assert_eq!(yaml, "---\nabc: 1.0.0\nbar: 1.0.0\nmno: 1.0.0\nzed: 1.0.0\n");
+    }
+
+    #[test]
+    fn package_repos_serialize_sorted() {
+        let mut repo = PackageRepo::new();
+        repo.add_version("zed", "0.1.0").unwrap();
+        repo.add_version("abc", "0.1.0").unwrap();
+        let yaml = to_canonical_string(&repo).unwrap();
+        assert!(yaml.find("abc").unwrap() < yaml.find("zed").unwrap());
+    }
+
+    #[test]
+    fn canonical_manifests_are_stable() {
+        let yaml = "name: foo-1.0.0\ndependencies:\n  zed: '0.1.0 <= 1.0.0'\n  abc: \"0.1.0\"\noptional:\n  gui: { qt: '5.0<6.0' }\n  docs: { sphinx: '1.8' }\n";
+        let canonical = canonicalize_manifest(yaml).unwrap();
+        assert_eq!(
+            canonical,
+            "---\nschema_version: 2\nname: foo-1.0.0\ndependencies:\n  abc: 0.1.0\n  zed: 0.1.0<=1.0.0\noptional:\n  docs:\n    sphinx: \"1.8\"\n  gui:\n    qt: 5.0<6.0\n"
+        );
+        assert!(!is_canonical_manifest(yaml).unwrap());
+        assert!(is_canonical_manifest(&canonical).unwrap());
+    }
+}
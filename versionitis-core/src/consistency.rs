@@ -0,0 +1,425 @@
+//! consistency.rs
+//!
+//! Cross check a PackageRepo (the released versions) against a
+//! ManifestRepo (the per version dependency manifests) and report
+//! anything which does not line up.
+//!
+use crate::{
+    manifest::{Manifest, Phase},
+    manifest_repo::ManifestRepo,
+    package_repo::PackageRepo,
+    version_number_interval::VersionNumberInterval,
+};
+use serde_derive::Serialize;
+use std::fmt;
+
+/// A single problem discovered while cross checking repos.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConsistencyIssue {
+    /// A released package version has no manifest
+    MissingManifest { package: String },
+    /// A manifest exists for a version which has not been released
+    UnreleasedManifest { manifest: String },
+    /// A manifest depends on a package name the PackageRepo knows nothing about
+    UnknownDependency { manifest: String, dependency: String },
+    /// A manifest depends on an interval which matches no released version
+    UnsatisfiableDependency {
+        manifest: String,
+        dependency: String,
+        interval: String,
+    },
+    /// A manifest declares a conflict with a package name the PackageRepo knows nothing about
+    UnknownConflict { manifest: String, conflict: String },
+    /// Every released version matching a dependency is excluded by the manifest's own conflicts
+    ConflictingDependency {
+        manifest: String,
+        dependency: String,
+        interval: String,
+    },
+}
+
+impl fmt::Display for ConsistencyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConsistencyIssue::MissingManifest { package } => {
+                write!(f, "{}: released version has no manifest", package)
+            }
+            ConsistencyIssue::UnreleasedManifest { manifest } => {
+                write!(f, "{}: manifest has no released version", manifest)
+            }
+            ConsistencyIssue::UnknownDependency { manifest, dependency } => {
+                write!(f, "{}: depends on unknown package '{}'", manifest, dependency)
+            }
+            ConsistencyIssue::UnsatisfiableDependency {
+                manifest,
+                dependency,
+                interval,
+            } => write!(
+                f,
+                "{}: dependency '{}: {}' matches no released version",
+                manifest, dependency, interval
+            ),
+            ConsistencyIssue::UnknownConflict { manifest, conflict } => {
+                write!(f, "{}: conflicts with unknown package '{}'", manifest, conflict)
+            }
+            ConsistencyIssue::ConflictingDependency {
+                manifest,
+                dependency,
+                interval,
+            } => write!(
+                f,
+                "{}: every release matching dependency '{}: {}' is declared a conflict",
+                manifest, dependency, interval
+            ),
+        }
+    }
+}
+
+/// The result of cross checking a PackageRepo against a ManifestRepo. Issues
+/// are sorted so that the report is stable from run to run.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ConsistencyReport {
+    issues: Vec<ConsistencyIssue>,
+}
+
+impl ConsistencyReport {
+    /// Retrieve the issues found
+    pub fn issues(&self) -> &[ConsistencyIssue] {
+        &self.issues
+    }
+
+    /// True if no issues were found
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Retrieve the number of issues found
+    pub fn len(&self) -> usize {
+        self.issues.len()
+    }
+
+    /// True if the report has no issues
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ConsistencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Cross check the released versions in `packages` against the manifests
+/// in `manifests`, reporting versions with no manifest, manifests with no
+/// released version, dependencies and conflicts on unknown packages, dependency
+/// intervals which match no released version, and dependencies which the
+/// manifest's own conflicts make impossible to satisfy. Dependencies on virtual
+/// packages must be matched by a version provided by a released manifest.
+///
+/// # Example
+///
+/// ```ignore
+/// let report = check(&package_repo, &manifest_repo);
+/// if !report.is_ok() {
+///     print!("{}", report);
+/// }
+/// ```
+pub fn check(packages: &PackageRepo, manifests: &ManifestRepo) -> ConsistencyReport {
+    let mut issues = Vec::new();
+
+    for versions in packages.packages.values() {
+        for package in versions {
+            let spec = package.spec();
+            if !manifests.has(spec.as_str()) {
+                issues.push(ConsistencyIssue::MissingManifest { package: spec });
+            }
+        }
+    }
+
+    for key in manifests.keys() {
+        // keys always have a manifest associated with them
+        let manifest = manifests.get_package(key).unwrap();
+        check_manifest(packages, manifests, manifest, &mut issues);
+    }
+
+    issues.sort();
+    ConsistencyReport { issues }
+}
+
+// check a single manifest against the released versions
+fn check_manifest(packages: &PackageRepo, manifests: &ManifestRepo, manifest: &Manifest, issues: &mut Vec<ConsistencyIssue>) {
+    let package = manifest.package();
+    let name = package.spec();
+    let released = packages
+        .packages
+        .get(package.name())
+        .is_some_and(|versions| versions.contains(package));
+    if !released {
+        issues.push(ConsistencyIssue::UnreleasedManifest { manifest: name });
+    }
+
+    let dependencies = Phase::ALL
+        .iter()
+        .flat_map(|phase| manifest.dependencies_for(*phase).iter());
+    for (dependency, interval) in dependencies {
+        issues.extend(check_dependency(packages, Some(manifests), manifest, dependency, interval));
+    }
+
+    for conflict in manifest.conflicts().keys() {
+        issues.extend(check_conflict(packages, manifest, conflict));
+    }
+}
+
+// check a single dependency of a manifest against the released versions. A
+// dependency on a virtual package is checked against the versions provided by
+// released manifests, if a ManifestRepo is supplied, and is otherwise unknown.
+pub(crate) fn check_dependency(
+    packages: &PackageRepo,
+    manifests: Option<&ManifestRepo>,
+    manifest: &Manifest,
+    dependency: &str,
+    interval: &VersionNumberInterval,
+) -> Option<ConsistencyIssue> {
+    let name = manifest.package().spec();
+    let providers = manifests.map(|manifests| manifests.providers(dependency)).unwrap_or_default();
+    match packages.packages.get(dependency) {
+        Some(versions) => {
+            let matching = versions
+                .iter()
+                .filter(|p| interval.contains(p.version_number()))
+                .collect::<Vec<_>>();
+            if matching.is_empty() {
+                Some(ConsistencyIssue::UnsatisfiableDependency {
+                    manifest: name,
+                    dependency: dependency.to_string(),
+                    interval: interval.to_string(),
+                })
+            } else if matching.iter().all(|p| manifest.conflicts_with(p)) {
+                Some(ConsistencyIssue::ConflictingDependency {
+                    manifest: name,
+                    dependency: dependency.to_string(),
+                    interval: interval.to_string(),
+                })
+            } else {
+                None
+            }
+        }
+        None if !providers.is_empty() => {
+            let satisfied = providers.iter().any(|(provided, provider)| {
+                interval.contains(provided.version_number())
+                    && packages.packages.get(provider.name())
+                        .is_some_and(|versions| versions.contains(provider.package()))
+            });
+            if satisfied {
+                None
+            } else {
+                Some(ConsistencyIssue::UnsatisfiableDependency {
+                    manifest: name,
+                    dependency: dependency.to_string(),
+                    interval: interval.to_string(),
+                })
+            }
+        }
+        None => Some(ConsistencyIssue::UnknownDependency {
+            manifest: name,
+            dependency: dependency.to_string(),
+        }),
+    }
+}
+
+// check that a conflict names a released package
+pub(crate) fn check_conflict(packages: &PackageRepo, manifest: &Manifest, conflict: &str) -> Option<ConsistencyIssue> {
+    if packages.packages.contains_key(conflict) {
+        None
+    } else {
+        Some(ConsistencyIssue::UnknownConflict {
+            manifest: manifest.package().spec(),
+            conflict: conflict.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        interval::Range, manifest_repo::ManifestArena, package::Package, traits::TrackPackages,
+        version_number_interval::VersionNumberInterval,
+    };
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    type VI = VersionNumberInterval;
+
+    fn setup_packages() -> PackageRepo {
+        let mut repo = PackageRepo::new();
+        repo.add_version("foo", "0.1.0").unwrap();
+        repo.add_version("foo", "1.0.0").unwrap();
+        repo.add_version("bar", "0.1.0").unwrap();
+        repo.add_version("abc", "0.1.0").unwrap();
+        repo.add_version("bla", "0.2.0").unwrap();
+        repo.add_version("bla", "0.3.0").unwrap();
+        repo
+    }
+
+    #[test]
+    fn test_resources_are_consistent() {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_resources");
+        path.push("manifest_repo");
+        let arena = ManifestArena::new();
+        let manifests = ManifestRepo::from_disk(path, &arena).unwrap();
+
+        let report = check(&setup_packages(), &manifests);
+        assert!(report.is_ok(), "unexpected issues:\n{}", report);
+    }
+
+    #[test]
+    fn reports_missing_and_unreleased_manifests() {
+        let mut packages = PackageRepo::new();
+        packages.add_version("foo", "0.1.0").unwrap();
+        packages.add_version("foo", "0.2.0").unwrap();
+
+        let arena = ManifestArena::new();
+        let mut manifests = ManifestRepo::new(&arena);
+        manifests.add_str("foo-0.1.0").unwrap();
+        manifests.add_str("foo-0.3.0").unwrap();
+
+        let report = check(&packages, &manifests);
+        assert_eq!(
+            report.issues(),
+            &[
+                ConsistencyIssue::MissingManifest {
+                    package: "foo-0.2.0".to_string()
+                },
+                ConsistencyIssue::UnreleasedManifest {
+                    manifest: "foo-0.3.0".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_and_unsatisfiable_dependencies() {
+        let packages = setup_packages();
+        let arena = ManifestArena::new();
+        let mut manifests = ManifestRepo::new(&arena);
+        for name in &["foo-0.1.0", "bar-0.1.0", "abc-0.1.0", "bla-0.2.0", "bla-0.3.0"] {
+            manifests.add_str(name).unwrap();
+        }
+        let mut manifest = Manifest::from_str("foo-1.0.0").unwrap();
+        manifest
+            .add_dependency("bla", VI::from_range(&Range::HalfOpen("0.4.0", "1.0.0")).unwrap())
+            .unwrap();
+        manifest
+            .add_dependency("nope", VI::from_range(&Range::Single("1.0.0")).unwrap())
+            .unwrap();
+        manifests.add(manifest);
+
+        let report = check(&packages, &manifests);
+        assert_eq!(
+            report.issues(),
+            &[
+                ConsistencyIssue::UnknownDependency {
+                    manifest: "foo-1.0.0".to_string(),
+                    dependency: "nope".to_string()
+                },
+                ConsistencyIssue::UnsatisfiableDependency {
+                    manifest: "foo-1.0.0".to_string(),
+                    dependency: "bla".to_string(),
+                    interval: "0.4.0<1.0.0".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_conflict_issues() {
+        let packages = setup_packages();
+        let arena = ManifestArena::new();
+        let mut manifests = ManifestRepo::new(&arena);
+        for name in &["foo-0.1.0", "bar-0.1.0", "abc-0.1.0", "bla-0.2.0", "bla-0.3.0"] {
+            manifests.add_str(name).unwrap();
+        }
+        let mut manifest = Manifest::from_str("foo-1.0.0").unwrap();
+        manifest
+            .add_dependency("bla", VI::from_range(&Range::HalfOpen("0.2.0", "1.0.0")).unwrap())
+            .unwrap();
+        manifest
+            .add_conflict("bla", VI::from_range(&Range::Open("0.2.0", "0.3.0")).unwrap())
+            .unwrap();
+        manifest
+            .add_conflict("nope", VI::from_range(&Range::Single("1.0.0")).unwrap())
+            .unwrap();
+        manifests.add(manifest);
+
+        let report = check(&packages, &manifests);
+        assert_eq!(
+            report.issues(),
+            &[
+                ConsistencyIssue::UnknownConflict {
+                    manifest: "foo-1.0.0".to_string(),
+                    conflict: "nope".to_string()
+                },
+                ConsistencyIssue::ConflictingDependency {
+                    manifest: "foo-1.0.0".to_string(),
+                    dependency: "bla".to_string(),
+                    interval: "0.2.0<1.0.0".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_virtual_dependencies_against_released_providers() {
+        let mut packages = PackageRepo::new();
+        packages.add_version("ocio_impl", "1.0.0").unwrap();
+        packages.add_version("app", "1.0.0").unwrap();
+
+        let arena = ManifestArena::new();
+        let mut manifests = ManifestRepo::new(&arena);
+        let mut provider = Manifest::from_str("ocio_impl-1.0.0").unwrap();
+        provider
+            .add_provides(Package::from_str("colormanagement-2.1").unwrap())
+            .unwrap();
+        manifests.add(provider);
+        let mut manifest = Manifest::from_str("app-1.0.0").unwrap();
+        manifest
+            .add_dependency("colormanagement", VI::from_range(&Range::HalfOpen("2.0", "3.0")).unwrap())
+            .unwrap();
+        manifests.add(manifest);
+        assert!(check(&packages, &manifests).is_ok());
+
+        let mut manifest = Manifest::from_str("app-1.0.0").unwrap();
+        manifest
+            .add_dependency("colormanagement", VI::from_range(&Range::HalfOpen("3.0", "4.0")).unwrap())
+            .unwrap();
+        manifests.add(manifest);
+        assert_eq!(
+            check(&packages, &manifests).issues(),
+            &[ConsistencyIssue::UnsatisfiableDependency {
+                manifest: "app-1.0.0".to_string(),
+                dependency: "colormanagement".to_string(),
+                interval: "3.0<4.0".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn can_serialize_report() {
+        let mut packages = PackageRepo::new();
+        packages.add_version("foo", "0.1.0").unwrap();
+        let arena = ManifestArena::new();
+        let manifests = ManifestRepo::new(&arena);
+
+        let report = check(&packages, &manifests);
+        let yaml = serde_yaml::to_string(&report).unwrap();
+        assert!(yaml.contains("kind: missing_manifest"));
+        assert!(yaml.contains("package: foo-0.1.0"));
+    }
+}
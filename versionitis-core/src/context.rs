@@ -0,0 +1,78 @@
+//! context.rs
+//!
+//! The environment in which a Manifest's optional and conditional
+//! dependencies are expanded into concrete requirements.
+//!
+use std::collections::{HashMap, HashSet};
+
+/// A Context tracks the features which have been requested, along with a map
+/// of facts about the environment (eg platform: linux, host: maya).
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Context {
+    features: HashSet<String>,
+    facts: HashMap<String, String>,
+}
+
+impl Context {
+    /// New up an empty Context, with no features and no facts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request a feature, activating any optional dependencies
+    /// associated with it.
+    pub fn add_feature<I: Into<String>>(&mut self, feature: I) {
+        self.features.insert(feature.into());
+    }
+
+    /// Test whether a feature has been requested
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+
+    /// Set a fact about the environment, replacing any previous value
+    pub fn set_fact<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.facts.insert(key.into(), value.into());
+    }
+
+    /// Retrieve the value of a fact, if it has been set
+    pub fn fact(&self, key: &str) -> Option<&str> {
+        self.facts.get(key).map(|value| value.as_str())
+    }
+
+    /// Test whether every key/value pair in `conditions` matches a fact
+    pub fn satisfies(&self, conditions: &HashMap<String, String>) -> bool {
+        conditions
+            .iter()
+            .all(|(key, value)| self.fact(key) == Some(value.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_track_features() {
+        let mut context = Context::new();
+        context.add_feature("gui");
+        assert!(context.has_feature("gui"));
+        assert!(!context.has_feature("docs"));
+    }
+
+    #[test]
+    fn satisfies_requires_every_condition() {
+        let mut context = Context::new();
+        context.set_fact("platform", "linux");
+        context.set_fact("host", "maya");
+
+        let mut conditions = HashMap::new();
+        conditions.insert("platform".to_string(), "linux".to_string());
+        assert!(context.satisfies(&conditions));
+
+        conditions.insert("host".to_string(), "houdini".to_string());
+        assert!(!context.satisfies(&conditions));
+
+        assert!(context.satisfies(&HashMap::new()));
+    }
+}
@@ -25,8 +25,42 @@ pub enum VersionitisError {
     AddVersionError(String),
     #[fail(display = "DuplicatePackageDependency: {}", _0)]
     DuplicatePackageDependency(String),
+    #[fail(display = "DuplicatePackageConflict: {}", _0)]
+    DuplicatePackageConflict(String),
+    #[fail(display = "DuplicateProvides: {}", _0)]
+    DuplicateProvides(String),
+    #[fail(display = "UnknownParent: {}", _0)]
+    UnknownParent(String),
+    #[fail(display = "InheritanceCycle: {}", _0)]
+    InheritanceCycle(String),
+    #[fail(display = "InvalidManifest: {}", _0)]
+    InvalidManifest(String),
+    #[fail(display = "UnsupportedSchemaVersion: {}", _0)]
+    UnsupportedSchemaVersion(String),
+    #[fail(display = "{}", _0)]
+    SerdeTomlError(String),
+    #[fail(display = "UnknownFormat: {}", _0)]
+    UnknownFormat(String),
+    #[fail(display = "InvalidSnapshot: {}", _0)]
+    InvalidSnapshot(String),
     #[fail(display = "ParseError: {}", _0)]
     ParseError(String),
+    #[fail(display = "{}", _0)]
+    SerdeJsonError(String),
+    #[fail(display = "UnknownLintRule: {}", _0)]
+    UnknownLintRule(String),
+    #[fail(display = "InvalidInterval: {}", _0)]
+    InvalidInterval(String),
+    #[fail(display = "UnresolvedDependency: {}", _0)]
+    UnresolvedDependency(String),
+    #[fail(display = "ConflictingDependency: {}", _0)]
+    ConflictingDependency(String),
+    #[fail(display = "IncompatiblePackages: {}", _0)]
+    IncompatiblePackages(String),
+    #[fail(display = "AmbiguousVariant: {}", _0)]
+    AmbiguousVariant(String),
+    #[fail(display = "DuplicateManifest: {}", _0)]
+    DuplicateManifest(String),
 }
 
 use std::num::ParseIntError;
@@ -42,6 +76,30 @@ impl From<serde_yaml::Error> for VersionitisError {
     }
 }
 
+impl From<serde_json::Error> for VersionitisError {
+    fn from(err: serde_json::Error) -> Self {
+        VersionitisError::SerdeJsonError(err.to_string())
+    }
+}
+
+impl From<toml::de::Error> for VersionitisError {
+    fn from(err: toml::de::Error) -> Self {
+        VersionitisError::SerdeTomlError(err.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for VersionitisError {
+    fn from(err: toml::ser::Error) -> Self {
+        VersionitisError::SerdeTomlError(err.to_string())
+    }
+}
+
+impl From<bincode::Error> for VersionitisError {
+    fn from(err: bincode::Error) -> Self {
+        VersionitisError::InvalidSnapshot(err.to_string())
+    }
+}
+
 impl From<std::io::Error> for VersionitisError {
     fn from(err: std::io::Error) -> Self {
         VersionitisError::IoError(err.to_string())
@@ -0,0 +1,175 @@
+//! format.rs
+//!
+//! Load and save serializable types as yaml, json or toml, choosing the
+//! format by file extension.
+//!
+use crate::{errors::VersionitisError, manifest::Manifest, schema};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A supported serialization format
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// All of the formats
+    pub const ALL: [Format; 3] = [Format::Yaml, Format::Json, Format::Toml];
+
+    /// Determine the format of a file from its extension (yaml, yml, json or
+    /// toml), returning an UnknownFormat error for anything else.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Format, VersionitisError> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => Ok(Format::Yaml),
+            Some("json") => Ok(Format::Json),
+            Some("toml") => Ok(Format::Toml),
+            _ => Err(VersionitisError::UnknownFormat(format!("{:?}", path))),
+        }
+    }
+
+    /// The preferred file extension for the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+            Format::Toml => "toml",
+        }
+    }
+
+    /// Deserialize a value from a str in this format
+    pub fn parse<T: DeserializeOwned>(self, input: &str) -> Result<T, VersionitisError> {
+        match self {
+            Format::Yaml => Ok(serde_yaml::from_str(input)?),
+            Format::Json => Ok(serde_json::from_str(input)?),
+            Format::Toml => Ok(toml::from_str(input)?),
+        }
+    }
+
+    /// Serialize a value to a String in this format. Yaml is written in the
+    /// canonical format and json is pretty printed.
+    pub fn render<T: Serialize>(self, value: &T) -> Result<String, VersionitisError> {
+        match self {
+            Format::Yaml => crate::canonical::to_canonical_string(value),
+            Format::Json => Ok(serde_json::to_string_pretty(value)? + "\n"),
+            Format::Toml => Ok(toml::to_string(value)?),
+        }
+    }
+
+    /// Load a manifest from a str in this format, upgrading it from older
+    /// schema versions.
+    pub fn parse_manifest(self, input: &str) -> Result<Manifest, VersionitisError> {
+        let document = schema::upgrade(self.parse(input)?)?;
+        Ok(serde_yaml::from_value(document)?)
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// Load a value from a file, choosing the format by extension
+///
+/// # Example
+///
+/// ```ignore
+/// let repo: PackageRepo = format::load("repo.json")?;
+/// ```
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, VersionitisError> {
+    let format = Format::from_path(&path)?;
+    format.parse(fs::read_to_string(path)?.as_str())
+}
+
+/// Save a value to a file, choosing the format by extension
+pub fn save<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> Result<(), VersionitisError> {
+    let format = Format::from_path(&path)?;
+    fs::write(path, format.render(value)?)?;
+    Ok(())
+}
+
+/// Load a manifest from a file, choosing the format by extension and
+/// upgrading it from older schema versions.
+pub fn load_manifest<P: AsRef<Path>>(path: P) -> Result<Manifest, VersionitisError> {
+    let format = Format::from_path(&path)?;
+    format.parse_manifest(fs::read_to_string(path)?.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interval_map::IntervalMap, manifest_builder::ManifestBuilder, package_repo::PackageRepo, traits::TrackPackages};
+
+    fn manifest() -> Manifest {
+        ManifestBuilder::new("fred-1.0.0")
+            .requires("foo", "0.1.0<1.0.0")
+            .requires("bar", "0.2.0")
+            .conflicts("bla", "0.1.0")
+            .provides("colormanagement-2.1")
+            .optional("gui", "qt", "5.0<6.0")
+            .when(&[("host", "maya")], "maya_sdk", "2019")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn can_detect_format_by_extension() {
+        assert_eq!(Format::from_path("repo/foo-1.0.0.yml"), Ok(Format::Yaml));
+        assert_eq!(Format::from_path("repo/foo-1.0.0.json"), Ok(Format::Json));
+        assert_eq!(Format::from_path("Versionitis.toml"), Ok(Format::Toml));
+        assert!(Format::from_path("README").is_err());
+    }
+
+    #[test]
+    fn manifests_round_trip_through_every_format() {
+        let manifest = manifest();
+        for format in Format::ALL.iter() {
+            let text = format.render(&manifest).unwrap();
+            assert_eq!(format.parse_manifest(&text).unwrap(), manifest, "format: {}", format);
+        }
+    }
+
+    #[test]
+    fn package_repos_and_interval_maps_round_trip_through_every_format() {
+        let mut repo = PackageRepo::new();
+        repo.add_version("foo", "0.1.0").unwrap();
+        repo.add_version("foo", "0.2.0").unwrap();
+        let map = manifest().dependencies().clone();
+        for format in Format::ALL.iter() {
+            let text = format.render(&repo).unwrap();
+            assert_eq!(format.parse::<PackageRepo>(&text).unwrap(), repo, "format: {}", format);
+            let text = format.render(&map).unwrap();
+            assert_eq!(format.parse::<IntervalMap>(&text).unwrap(), map, "format: {}", format);
+        }
+    }
+
+    #[test]
+    fn format_errors_map_into_versionitis_errors() {
+        assert!(matches!(Format::Json.parse::<IntervalMap>("{"), Err(VersionitisError::SerdeJsonError(_))));
+        assert!(matches!(Format::Toml.parse::<IntervalMap>("= 1"), Err(VersionitisError::SerdeTomlError(_))));
+        assert!(matches!(Format::Yaml.parse::<IntervalMap>("[: "), Err(VersionitisError::SerdeYamlError(_))));
+    }
+
+    #[test]
+    fn malformed_manifests_are_errors() {
+        let inputs = [
+            "",
+            "[]",
+            "name: ''\n",
+            "name: foo-1..2\n",
+            "name: foo-1.0.0\ndependencies:\n  bar: ''\n",
+            "name: foo-1.0.0\ndependencies:\n  bar: '1..2'\n",
+            "name: foo-1.0.0\ndependencies:\n  bar: '1.2.3^'\n",
+            "name: foo-1.0.0\nconditional:\n  - when: {}\n    dependencies: {bar: '1^'}\n",
+        ];
+        for input in inputs.iter() {
+            assert!(Format::Yaml.parse_manifest(input).is_err(), "input: {:?}", input);
+        }
+    }
+}
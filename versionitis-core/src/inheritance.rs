@@ -0,0 +1,334 @@
+//! inheritance.rs
+//!
+//! Resolve manifests which extend other manifests or named templates
+//! into flattened, effective manifests.
+//!
+use crate::{errors::VersionitisError, format::Format, manifest::Manifest, package::Package, schema};
+use std::collections::HashMap;
+use std::fs;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// The name of the directory, within a manifest directory, holding templates
+pub const TEMPLATE_DIR: &str = "templates";
+
+/// The ManifestResolver stores manifests as written, along with named templates,
+/// and flattens each manifest's `extends` chain into an effective manifest.
+///
+/// A template is a manifest without a `name`, stored in the `templates`
+/// directory of a manifest directory as `<template name>.yaml`. Template names
+/// follow the same rules as package names.
+#[derive(Debug, Default)]
+pub struct ManifestResolver {
+    manifests: HashMap<Package, Manifest>,
+    templates: HashMap<String, Manifest>,
+}
+
+impl ManifestResolver {
+    /// New up an empty ManifestResolver
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the manifests in a directory, along with the templates in its
+    /// `templates` subdirectory. Manifests and templates may be written in
+    /// yaml, json or toml, as indicated by their extension. Files with any
+    /// other extension are skipped.
+    ///
+    /// Files are read and parsed in parallel, then added in order of path, so
+    /// that the result, and the error returned should several files fail to
+    /// load, do not depend upon scheduling. A file which fails to parse is
+    /// reported along with its path, and two files declaring the same package
+    /// are a DuplicateManifest error.
+    pub fn from_disk<P: Into<PathBuf>>(path: P) -> Result<Self, VersionitisError> {
+        let path = path.into();
+        if !path.is_dir() {
+            return Err(VersionitisError::IoError(format!("path: {:?} does not exist", path)));
+        }
+
+        let mut paths = files(&path)?;
+        let template_dir = path.join(TEMPLATE_DIR);
+        if template_dir.is_dir() {
+            paths.extend(files(&template_dir)?);
+        }
+        let contents = paths
+            .into_par_iter()
+            .map(|path| fs::read_to_string(&path).map(|contents| (path, contents)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_contents(&path, &contents)
+    }
+
+    /// Build a resolver from the contents of files which have already been
+    /// read from a manifest directory, keyed by path. Files in the directory's
+    /// `templates` subdirectory are read as templates. Like `from_disk`, files
+    /// are parsed in parallel and added in the order supplied.
+    pub(crate) fn from_contents(dir: &Path, contents: &[(PathBuf, String)]) -> Result<Self, VersionitisError> {
+        let template_dir = dir.join(TEMPLATE_DIR);
+        let parsed = contents
+            .par_iter()
+            .map(|(path, contents)| {
+                let format = Format::from_path(path)?;
+                let in_file = |err: VersionitisError| VersionitisError::InvalidManifest(format!("{}: {}", path.display(), err));
+                if path.parent() != Some(template_dir.as_path()) {
+                    return Ok(Parsed::Manifest(Box::new(format.parse_manifest(contents).map_err(in_file)?)));
+                }
+                let name = path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .ok_or_else(|| VersionitisError::IoError(format!("invalid template path: {:?}", path)))?
+                    .to_string();
+                Ok(Parsed::Template(name, format.parse(contents).map_err(in_file)?))
+            })
+            .collect::<Vec<Result<_, VersionitisError>>>();
+
+        let mut resolver = Self::new();
+        let mut declared = HashMap::new();
+        for ((path, _), parsed) in contents.iter().zip(parsed) {
+            match parsed? {
+                Parsed::Manifest(manifest) => {
+                    if let Some(first) = declared.insert(*manifest.package(), path) {
+                        return Err(VersionitisError::DuplicateManifest(format!(
+                            "{} is declared by both {} and {}",
+                            manifest.package(),
+                            first.display(),
+                            path.display()
+                        )));
+                    }
+                    resolver.add_manifest(*manifest)
+                }
+                Parsed::Template(name, document) => resolver.add_template(name, document)?,
+            }
+        }
+        Ok(resolver)
+    }
+
+    /// Add a manifest, replacing any existing manifest for the same package
+    pub fn add_manifest(&mut self, manifest: Manifest) {
+        self.manifests.insert(*manifest.package(), manifest);
+    }
+
+    /// Add a named template, given its yaml. The yaml takes the form of a
+    /// manifest without a `name`.
+    pub fn add_template_str<I: Into<String>>(&mut self, name: I, yaml: &str) -> Result<(), VersionitisError> {
+        self.add_template(name, serde_yaml::from_str(yaml)?)
+    }
+
+    /// Add a named template, given its document, in the form of a manifest
+    /// without a `name`.
+    pub fn add_template<I: Into<String>>(&mut self, name: I, document: serde_yaml::Value) -> Result<(), VersionitisError> {
+        let name = name.into();
+        let mut value = schema::upgrade(document)?;
+        let mapping = value.as_mapping_mut().ok_or_else(|| {
+            VersionitisError::ParseError(format!("template '{}' is not a mapping", name))
+        })?;
+        // templates are stored as manifests of a placeholder package, which
+        // is never exposed, as only their declarations are inherited.
+        mapping.insert("name".into(), format!("{}-0", name).into());
+        let template: Manifest = serde_yaml::from_value(value)?;
+        self.templates.insert(name, template);
+        Ok(())
+    }
+
+    /// Retrieve the manifest, as written, for a package
+    pub fn get(&self, package: &Package) -> Option<&Manifest> {
+        self.manifests.get(package)
+    }
+
+    /// Retrieve the flattened, effective manifest for a package. Each parent in
+    /// the `extends` chain is flattened in turn and its declarations inherited.
+    /// The effective manifest does not extend anything.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let manifest = resolver.effective(&Package::from_str("foo-1.0.0")?)?;
+    /// println!("{}", serde_yaml::to_string(&manifest)?);
+    /// ```
+    pub fn effective(&self, package: &Package) -> Result<Manifest, VersionitisError> {
+        let manifest = self.manifests.get(package)
+            .ok_or_else(|| VersionitisError::UnknownPackage(package.spec()))?;
+        self.flatten(manifest, &mut vec![package.spec()])
+    }
+
+    /// Flatten every manifest, returning the effective manifests sorted by package
+    pub fn resolve_all(&self) -> Result<Vec<Manifest>, VersionitisError> {
+        let mut packages = self.manifests.keys().collect::<Vec<&Package>>();
+        packages.sort();
+        packages.into_iter().map(|package| self.effective(package)).collect()
+    }
+
+    // flatten a manifest, tracking the chain of parents visited so far in
+    // order to detect cycles
+    fn flatten(&self, manifest: &Manifest, chain: &mut Vec<String>) -> Result<Manifest, VersionitisError> {
+        let mut result = manifest.clone();
+        let parent = match manifest.extends() {
+            Some(parent) => parent,
+            None => return Ok(result),
+        };
+        // normalize package specs so that variant qualifiers may be in any order
+        let (key, parent_manifest) = match Package::from_str(parent) {
+            Ok(package) => (package.spec(), self.manifests.get(&package)),
+            Err(_) => (parent.to_string(), self.templates.get(parent)),
+        };
+        if chain.contains(&key) {
+            chain.push(key);
+            return Err(VersionitisError::InheritanceCycle(chain.join(" -> ")));
+        }
+        let parent_manifest = parent_manifest.ok_or_else(|| {
+            VersionitisError::UnknownParent(format!("{} extends {}", chain.last().unwrap(), parent))
+        })?;
+        chain.push(key);
+        let parent = self.flatten(parent_manifest, chain)?;
+        chain.pop();
+        result.inherit(&parent);
+        result.set_extends(None::<String>);
+        Ok(result)
+    }
+}
+
+// A manifest or template file, once parsed
+enum Parsed {
+    Manifest(Box<Manifest>),
+    Template(String, serde_yaml::Value),
+}
+
+// list the files in a directory with a recognised extension, sorted by path
+fn files(dir: &Path) -> Result<Vec<PathBuf>, VersionitisError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && Format::from_path(&path).is_ok() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interval::Range, manifest::Phase, test_util::ScratchDir, version_number_interval::VersionNumberInterval};
+    use std::str::FromStr;
+
+    type VI = VersionNumberInterval;
+
+    fn manifest(name: &str, extends: Option<&str>, deps: &[(&str, &str)]) -> Manifest {
+        let mut manifest = Manifest::from_str(name).unwrap();
+        manifest.set_extends(extends);
+        for (dep, version) in deps {
+            manifest.add_dependency(*dep, VI::from_range(&Range::Single(*version)).unwrap()).unwrap();
+        }
+        manifest
+    }
+
+    fn dependency(manifest: &Manifest, name: &str) -> Option<String> {
+        manifest.dependencies().get(name).map(|interval| interval.to_string())
+    }
+
+    #[test]
+    fn child_overrides_parent_dependencies() {
+        let mut resolver = ManifestResolver::new();
+        resolver.add_manifest(manifest("foo-1.0.0", None, &[("bar", "1.0.0"), ("bla", "1.0.0")]));
+        resolver.add_manifest(manifest("foo-1.1.0", Some("foo-1.0.0"), &[("bar", "2.0.0")]));
+
+        let effective = resolver.effective(&Package::from_str("foo-1.1.0").unwrap()).unwrap();
+        assert_eq!(dependency(&effective, "bar"), Some("2.0.0".to_string()));
+        assert_eq!(dependency(&effective, "bla"), Some("1.0.0".to_string()));
+        assert_eq!(effective.extends(), None);
+        assert_eq!(effective.package(), &Package::from_str("foo-1.1.0").unwrap());
+    }
+
+    #[test]
+    fn can_extend_templates() {
+        let mut resolver = ManifestResolver::new();
+        resolver.add_template_str("cpp_base", "dependencies:\n  boost: '1.70'\nbuild_dependencies:\n  cmake: '3.14'\n").unwrap();
+        resolver.add_template_str("cpp_tests", "extends: cpp_base\ndependencies:\n  gtest: '1.8'\n").unwrap();
+        resolver.add_manifest(manifest("foo-1.0.0", Some("cpp_tests"), &[]));
+
+        let effective = resolver.effective(&Package::from_str("foo-1.0.0").unwrap()).unwrap();
+        assert_eq!(dependency(&effective, "boost"), Some("1.70".to_string()));
+        assert_eq!(dependency(&effective, "gtest"), Some("1.8".to_string()));
+        assert!(effective.dependencies_for(Phase::Build).contains_key("cmake"));
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let mut resolver = ManifestResolver::new();
+        resolver.add_manifest(manifest("foo-1.0.0", Some("foo-1.1.0"), &[]));
+        resolver.add_manifest(manifest("foo-1.1.0", Some("foo-1.0.0"), &[]));
+        let result = resolver.effective(&Package::from_str("foo-1.0.0").unwrap());
+        assert_eq!(
+            result,
+            Err(VersionitisError::InheritanceCycle("foo-1.0.0 -> foo-1.1.0 -> foo-1.0.0".to_string()))
+        );
+    }
+
+    #[test]
+    fn unknown_parent_is_an_error() {
+        let mut resolver = ManifestResolver::new();
+        resolver.add_manifest(manifest("foo-1.0.0", Some("nope"), &[]));
+        assert_eq!(
+            resolver.resolve_all(),
+            Err(VersionitisError::UnknownParent("foo-1.0.0 extends nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn parallel_loading_is_deterministic() {
+        let dir = ScratchDir::new("parallel");
+        for minor in 0..64 {
+            let yaml = format!("name: foo-1.{}.0\ndependencies:\n  bar: '0.{}.0'\n", minor, minor);
+            fs::write(dir.join(format!("foo-1.{}.0.yaml", minor)), yaml).unwrap();
+        }
+        let expected = ManifestResolver::from_disk(dir.path()).unwrap().resolve_all().unwrap();
+        assert_eq!(expected.len(), 64);
+        for _ in 0..4 {
+            assert_eq!(ManifestResolver::from_disk(dir.path()).unwrap().resolve_all().unwrap(), expected);
+        }
+
+        // the error reported is that of the first bad file by path
+        let first = "name: a-0.1.0\ndependencies: [";
+        fs::write(dir.join("a-0.1.0.yaml"), first).unwrap();
+        fs::write(dir.join("z-0.1.0.yaml"), "name: z-0.1.0\ndependencies: nope\n").unwrap();
+        let expected = VersionitisError::InvalidManifest(format!(
+            "{}: {}",
+            dir.join("a-0.1.0.yaml").display(),
+            Format::Yaml.parse_manifest(first).unwrap_err()
+        ));
+        for _ in 0..4 {
+            assert_eq!(ManifestResolver::from_disk(dir.path()).unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn duplicate_manifests_are_an_error() {
+        let dir = ScratchDir::new("duplicate");
+        fs::write(dir.join("foo.yaml"), "name: foo-1.0.0\ndependencies: {}\n").unwrap();
+        fs::write(dir.join("foo.json"), "{\"name\": \"foo-1.0.0\", \"dependencies\": {}}").unwrap();
+        assert_eq!(
+            ManifestResolver::from_disk(dir.path()).unwrap_err(),
+            VersionitisError::DuplicateManifest(format!(
+                "foo-1.0.0 is declared by both {} and {}",
+                dir.join("foo.json").display(),
+                dir.join("foo.yaml").display()
+            ))
+        );
+    }
+
+    #[test]
+    fn can_load_templates_from_disk() {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_resources");
+        path.push("manifest_inheritance");
+        let resolver = ManifestResolver::from_disk(path).unwrap();
+        let manifests = resolver.resolve_all().unwrap();
+        assert_eq!(manifests.len(), 2);
+        let effective = &manifests[1];
+        assert_eq!(effective.package().spec(), "foo-1.1.0");
+        assert_eq!(dependency(effective, "bar"), Some("0.2.0".to_string()));
+        assert_eq!(dependency(effective, "bla"), Some("0.2.0<=1.0.0".to_string()));
+        assert_eq!(dependency(effective, "abc"), Some("0.1.0".to_string()));
+    }
+}
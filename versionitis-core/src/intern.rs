@@ -0,0 +1,235 @@
+//! intern.rs
+//!
+//! Intern package names, version numbers and variants so that each is stored
+//! once, and may be referred to by a small Copy handle.
+//!
+//! Interned values live for the life of the process. The set of names and
+//! versions in a studio is bounded, so this trades a fixed amount of memory
+//! for storing each one once, rather than once per Package. Long-running
+//! processes which see an unbounded stream of new versions, such as a
+//! `watch::RepoWatcher`, grow accordingly; see the `watch` module.
+//!
+use serde::{
+    de::{self, Deserializer, Visitor},
+    ser::{Serialize, Serializer},
+    Deserialize,
+};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::{OnceLock, RwLock};
+
+// A set of values leaked for the life of the process. Lookups take a read
+// lock, so that parallel loading only contends when a new value is seen.
+struct Interner<K: 'static, V: 'static> {
+    map: OnceLock<RwLock<HashMap<K, &'static V>>>,
+}
+
+impl<K: Eq + Hash, V> Interner<K, V> {
+    const fn new() -> Self {
+        Self { map: OnceLock::new() }
+    }
+
+    fn intern<Q>(&self, key: &Q, make: impl FnOnce(&Q) -> (K, V)) -> &'static V
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let map = self.map.get_or_init(|| RwLock::new(HashMap::new()));
+        if let Some(value) = map.read().expect("interner poisoned").get(key) {
+            return value;
+        }
+        let mut map = map.write().expect("interner poisoned");
+        if let Some(value) = map.get(key) {
+            return value;
+        }
+        let (key, value) = make(key);
+        let value: &'static V = Box::leak(Box::new(value));
+        map.insert(key, value);
+        value
+    }
+
+    fn len(&self) -> usize {
+        self.map.get().map(|map| map.read().expect("interner poisoned").len()).unwrap_or(0)
+    }
+}
+
+static NAMES: Interner<&'static str, &'static str> = Interner::new();
+static VERSIONS: Interner<Vec<u16>, InternedVersion> = Interner::new();
+static VARIANTS: Interner<Vec<Name>, Vec<Name>> = Interner::new();
+
+/// An interned str, such as a package name or variant qualifier. A Name
+/// derefs to str, and compares, orders and hashes as its str does, so it may
+/// be used to key a map which is looked up by &str.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Name(&'static str);
+
+impl Name {
+    /// Intern a str, returning its Name
+    pub fn new(name: &str) -> Self {
+        Name(NAMES.intern(name, |name| {
+            let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+            (name, name)
+        }))
+    }
+
+    /// Retrieve the interned str
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        self.0
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name::new(name)
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.0, f)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl Serialize for Name {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.0)
+    }
+}
+
+// NameVisitor used for serde deserialization
+struct NameVisitor;
+
+impl<'de> Visitor<'de> for NameVisitor {
+    type Value = Name;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a str")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Name::new(value))
+    }
+}
+
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D>(deserializer: D) -> Result<Name, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(NameVisitor)
+    }
+}
+
+/// The interned digits of a version number, along with their dotted form
+#[derive(Debug)]
+pub struct InternedVersion {
+    pub value: Vec<u16>,
+    pub name: String,
+}
+
+/// Intern the digits of a version number
+pub fn version(value: &[u16]) -> &'static InternedVersion {
+    VERSIONS.intern(value, |value| {
+        let name = value.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(".");
+        (value.to_vec(), InternedVersion { value: value.to_vec(), name })
+    })
+}
+
+/// Intern a variant, which is expected to be sorted and deduplicated
+pub fn variant(qualifiers: &[Name]) -> &'static [Name] {
+    VARIANTS.intern(qualifiers, |qualifiers| (qualifiers.to_vec(), qualifiers.to_vec())).as_slice()
+}
+
+/// Counts of the distinct values interned so far
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InternStats {
+    pub names: usize,
+    pub versions: usize,
+    pub variants: usize,
+}
+
+/// Retrieve counts of the distinct names, versions and variants interned so far
+pub fn stats() -> InternStats {
+    InternStats {
+        names: NAMES.len(),
+        versions: VERSIONS.len(),
+        variants: VARIANTS.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_stored_once() {
+        let first = Name::new(&String::from("interned-name"));
+        let second = Name::new("interned-name");
+        assert_eq!(first, second);
+        assert!(std::ptr::eq(first.as_str(), second.as_str()));
+        assert_eq!(first, "interned-name");
+    }
+
+    #[test]
+    fn names_key_maps_looked_up_by_str() {
+        let mut map = HashMap::new();
+        map.insert(Name::new("foo"), 1);
+        assert_eq!(map.get("foo"), Some(&1));
+        assert!(Name::new("abc") < Name::new("abd"));
+    }
+
+    #[test]
+    fn versions_and_variants_are_stored_once() {
+        assert!(std::ptr::eq(version(&[1, 2, 3]), version(&[1, 2, 3][..])));
+        assert_eq!(version(&[1, 2, 3]).name, "1.2.3");
+        let qualifiers = [Name::new("gcc9"), Name::new("linux")];
+        assert!(std::ptr::eq(variant(&qualifiers), variant(&qualifiers[..])));
+    }
+}
@@ -4,6 +4,7 @@
 //! generic type T.
 use std::{fmt::Debug, hash::Hash};
 use serde_derive::{Deserialize, Serialize};
+use crate::errors::VersionitisError;
 
 /// Enum wrapping possible inputs to PackageInterval::from_src
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -13,18 +14,99 @@ pub enum Range<'a> {
     Open(&'a str, &'a str),
 }
 
-/// Define an Interval enum which may be a Single value, HalfOpen, or Open.
+/// Define an Interval enum which may be a Single value, HalfOpen, Open, or Empty.
 /// A HalfOpen value's lower bound is inclusive, whereas an Open bound's lower
-/// and upper bounds are inclusive.
+/// and upper bounds are inclusive. An Empty interval contains nothing.
 #[derive(Debug, PartialEq, Eq, Hash, Clone /*, Deserialize*/)]
 //#[serde(rename_all = "snake_case")]
 pub enum Interval<T: Eq + Ord + Debug + Hash + Clone> {
     Single(T),
     HalfOpen { start: T, end: T },
     Open { start: T, end: T },
+    Empty,
 }
 
 impl<T: Eq + Ord + Debug + Hash + Clone> Interval<T> {
+    /// Construct a HalfOpen interval, verifying that it is not inverted
+    /// (start > end) or empty (start == end).
+    pub fn half_open(start: T, end: T) -> Result<Self, VersionitisError> {
+        if start >= end {
+            return Err(VersionitisError::InvalidInterval(format!(
+                "{:?}<{:?} contains no values", start, end
+            )));
+        }
+        Ok(Interval::HalfOpen { start, end })
+    }
+
+    /// Construct an Open interval, verifying that it is not inverted (start > end).
+    pub fn open(start: T, end: T) -> Result<Self, VersionitisError> {
+        if start > end {
+            return Err(VersionitisError::InvalidInterval(format!(
+                "{:?}<={:?} has a start greater than its end", start, end
+            )));
+        }
+        Ok(Interval::Open { start, end })
+    }
+
+    /// Construct an interval which contains nothing. Prefer this to
+    /// building an inverted interval by hand.
+    pub fn empty() -> Self {
+        Interval::Empty
+    }
+
+    /// Test whether the interval contains no values at all. This is
+    /// true of the Empty variant, as well as of inverted intervals.
+    pub fn is_empty(&self) -> bool {
+        match *self {
+            Interval::Single(_) => false,
+            Interval::HalfOpen { ref start, ref end } => start >= end,
+            Interval::Open { ref start, ref end } => start > end,
+            Interval::Empty => true,
+        }
+    }
+
+    /// Compute the interval containing the values common to both self
+    /// and other. If they do not overlap, the result is Empty.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (start1, end1, inclusive1) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Interval::Empty,
+        };
+        let (start2, end2, inclusive2) = match other.bounds() {
+            Some(bounds) => bounds,
+            None => return Interval::Empty,
+        };
+        let start = std::cmp::max(start1, start2).clone();
+        let (end, inclusive) = if end1 < end2 {
+            (end1, inclusive1)
+        } else if end2 < end1 {
+            (end2, inclusive2)
+        } else {
+            (end1, inclusive1 && inclusive2)
+        };
+        let end = end.clone();
+        if inclusive && start == end {
+            Interval::Single(start)
+        } else if inclusive && start < end {
+            Interval::Open { start, end }
+        } else if !inclusive && start < end {
+            Interval::HalfOpen { start, end }
+        } else {
+            Interval::Empty
+        }
+    }
+
+    // Retrieve the lower bound, upper bound, and whether the upper bound
+    // is inclusive. The lower bound is always inclusive. Empty has no bounds.
+    fn bounds(&self) -> Option<(&T, &T, bool)> {
+        match *self {
+            Interval::Single(ref v) => Some((v, v, true)),
+            Interval::HalfOpen { ref start, ref end } => Some((start, end, false)),
+            Interval::Open { ref start, ref end } => Some((start, end, true)),
+            Interval::Empty => None,
+        }
+    }
+
     /// Test whether a the Interval contains a specific
     /// value T.
     pub fn contains(&self, value: &T) -> bool {
@@ -32,6 +114,7 @@ impl<T: Eq + Ord + Debug + Hash + Clone> Interval<T> {
             Interval::Single(ref v) => value == v,
             Interval::HalfOpen { ref start, ref end } => value >= start && value < end,
             Interval::Open { ref start, ref end } => value >= start && value <= end,
+            Interval::Empty => false,
         }
     }
 }
@@ -42,7 +125,7 @@ mod tests {
 
     mod packagetests {
         use super::*;
-        use crate::package::owned::Package;
+        use crate::package::Package;
 
         #[test]
         fn single_contains_true() {
@@ -335,5 +418,77 @@ mod tests {
             let expected = VersionNumber::from_string("0.4.1").unwrap();
             assert_eq!(result[result.len() - 1], &expected);
         }
+
+        #[test]
+        fn empty_contains_nothing() {
+            let ident = Interval::<VersionNumber>::empty();
+            let test = VersionNumber::from_string("0.1.0").unwrap();
+            assert!(!ident.contains(&test));
+            assert!(ident.is_empty());
+        }
+
+        #[test]
+        fn half_open_rejects_inverted_and_empty_bounds() {
+            let v1 = VersionNumber::from_string("1.0.0").unwrap();
+            let v2 = VersionNumber::from_string("2.0.0").unwrap();
+            assert!(Interval::half_open(v1, v2).is_ok());
+            assert_eq!(
+                Interval::half_open(v2, v1),
+                Err(VersionitisError::InvalidInterval(
+                    "2.0.0<1.0.0 contains no values".to_string()
+                ))
+            );
+            assert!(Interval::half_open(v1, v1).is_err());
+        }
+
+        #[test]
+        fn open_rejects_inverted_bounds() {
+            let v1 = VersionNumber::from_string("1.0.0").unwrap();
+            let v2 = VersionNumber::from_string("2.0.0").unwrap();
+            assert!(Interval::open(v1, v2).is_ok());
+            assert!(Interval::open(v1, v1).is_ok());
+            assert_eq!(
+                Interval::open(v2, v1),
+                Err(VersionitisError::InvalidInterval(
+                    "2.0.0<=1.0.0 has a start greater than its end".to_string()
+                ))
+            );
+        }
+
+        #[test]
+        fn intersection_of_overlapping_intervals() {
+            let vn = |s: &str| VersionNumber::from_string(s).unwrap();
+            let half_open = Interval::HalfOpen { start: vn("1.0.0"), end: vn("2.0.0") };
+            let open = Interval::Open { start: vn("1.5.0"), end: vn("3.0.0") };
+            assert_eq!(
+                half_open.intersection(&open),
+                Interval::HalfOpen { start: vn("1.5.0"), end: vn("2.0.0") }
+            );
+            let open2 = Interval::Open { start: vn("0.5.0"), end: vn("1.5.0") };
+            assert_eq!(
+                open.intersection(&open2),
+                Interval::Single(vn("1.5.0"))
+            );
+            let single = Interval::Single(vn("1.2.0"));
+            assert_eq!(half_open.intersection(&single), single);
+        }
+
+        #[test]
+        fn intersection_of_disjoint_intervals_is_empty() {
+            let vn = |s: &str| VersionNumber::from_string(s).unwrap();
+            let half_open = Interval::HalfOpen { start: vn("1.0.0"), end: vn("2.0.0") };
+            let single = Interval::Single(vn("2.0.0"));
+            assert_eq!(half_open.intersection(&single), Interval::Empty);
+            assert_eq!(half_open.intersection(&Interval::empty()), Interval::Empty);
+        }
+
+        #[test]
+        fn hand_built_inverted_interval_is_empty() {
+            let ident = Interval::HalfOpen {
+                start: VersionNumber::from_string("2.0.0").unwrap(),
+                end: VersionNumber::from_string("1.0.0").unwrap(),
+            };
+            assert!(ident.is_empty());
+        }
     }
 }
@@ -2,7 +2,8 @@
 //! interval_map.rs
 //!
 //! IntervalMap
-use serde_derive::{Deserialize, Serialize};
+use serde::{Serialize, Serializer};
+use serde_derive::Deserialize;
 use std::collections::{ HashMap, hash_map::{ Keys, Values, ValuesMut, Iter, IterMut, Entry, Drain, RandomState } };
 use crate::version_number_interval::{VersionNumberInterval};
 use std::fmt;
@@ -12,10 +13,21 @@ use std::cmp::{ PartialEq, Eq };
 type _IntervalMap = HashMap<String, VersionNumberInterval>;
 
 /// IntervalMap newtype struct which presents a HashMap api while implementing
-/// Debug, PartialEq and Eq of the wrapped contents.
-#[derive(Deserialize, Serialize)]
+/// Debug, PartialEq and Eq of the wrapped contents. It serializes with its keys
+/// sorted, so that the output is stable.
+#[derive(Deserialize, Clone)]
+#[serde(transparent)]
 pub struct IntervalMap(_IntervalMap);
 
+impl Serialize for IntervalMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        crate::canonical::serialize_sorted(&self.0, serializer)
+    }
+}
+
 impl PartialEq for IntervalMap {
     fn eq(&self, other: &IntervalMap) -> bool {
         if self.len() == other.len() {
@@ -44,6 +56,12 @@ impl fmt::Debug for IntervalMap {
     }
 }
 
+impl Default for IntervalMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl IntervalMap {
     pub fn new() -> Self {
         Self (
@@ -101,6 +119,10 @@ impl IntervalMap {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn drain(&mut self) -> Drain<String, VersionNumberInterval> {
         self.0.drain()
     }
@@ -133,6 +155,21 @@ impl IntervalMap {
         self.0.remove(k)
     }
 
+    /// Insert a key-value pair into the map. If the map already has the key,
+    /// the stored interval is narrowed to the intersection of the two, which
+    /// may be empty.
+    pub fn insert_intersection<K: Into<String>>(&mut self, k: K, v: VersionNumberInterval) {
+        match self.0.entry(k.into()) {
+            Entry::Occupied(mut entry) => {
+                let narrowed = entry.get().intersection(&v);
+                entry.insert(narrowed);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(v);
+            }
+        }
+    }
+
 }
 
 /*
@@ -1,15 +1,33 @@
 pub mod traits;
+pub mod intern;
 pub mod version_number;
 pub mod interval;
 pub mod package_map;
 pub mod package;
 pub mod errors;
 pub mod manifest;
+pub mod schema;
+pub mod format;
+pub mod snapshot;
 pub mod package_repo;
 pub mod vernum_interval_parser;
 pub mod interval_map;
 pub mod version_number_interval;
 pub mod package_version_parser;
 pub mod manifest_repo;
+pub mod manifest_cache;
+pub mod watch;
+pub mod synthetic;
+pub mod manifest_edit;
+pub mod canonical;
+pub mod manifest_builder;
+pub mod inheritance;
+pub mod consistency;
+pub mod lint;
+pub mod context;
+#[cfg(test)]
+mod properties;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
 pub use crate::package_repo::PackageRepo;
@@ -0,0 +1,476 @@
+//! lint.rs
+//!
+//! Lint Manifests and IntervalMaps for suspicious dependency
+//! specifications. Each rule has an id and a configurable Severity.
+//!
+use crate::{
+    errors::VersionitisError,
+    interval::Interval,
+    interval_map::IntervalMap,
+    manifest::{Manifest, Phase},
+    package_version_parser::PackageVersionParser,
+    version_number_interval::VersionNumberInterval,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::TryFrom, fmt};
+
+/// How seriously a lint rule should be taken. Allow silences the rule,
+/// Warn reports it, and Deny reports it as an error. A severity is written
+/// the same way in text output, json output and configuration.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Allow => write!(f, "allow"),
+            Severity::Warn => write!(f, "warn"),
+            Severity::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// The lint rules known to the linter.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum LintRule {
+    /// a dependency pinned to a Single version
+    ExactPin,
+    /// an interval whose start is greater than its end
+    InvertedInterval,
+    /// an interval which is not inverted, yet contains nothing (eg 1.0.0<1.0.0)
+    EmptyRange,
+    /// a manifest which depends upon its own package
+    SelfDependency,
+    /// a manifest whose name does not parse as name-version
+    InvalidName,
+}
+
+impl LintRule {
+    /// All of the rules, in reporting order
+    pub const ALL: [LintRule; 5] = [
+        LintRule::ExactPin,
+        LintRule::InvertedInterval,
+        LintRule::EmptyRange,
+        LintRule::SelfDependency,
+        LintRule::InvalidName,
+    ];
+
+    /// The id used to refer to the rule in configuration and output
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::ExactPin => "exact-pin",
+            LintRule::InvertedInterval => "inverted-interval",
+            LintRule::EmptyRange => "empty-range",
+            LintRule::SelfDependency => "self-dependency",
+            LintRule::InvalidName => "invalid-name",
+        }
+    }
+
+    /// Look up a rule given its id
+    pub fn from_id(id: &str) -> Option<LintRule> {
+        LintRule::ALL.iter().find(|rule| rule.id() == id).cloned()
+    }
+
+    /// The severity used when the configuration does not mention the rule
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            LintRule::ExactPin => Severity::Warn,
+            _ => Severity::Deny,
+        }
+    }
+}
+
+/// Map rule ids to severities. Rules which are not mentioned fall back
+/// to their default severity.
+///
+/// Configuration may be deserialized from yaml:
+///
+/// ```yaml
+/// exact-pin: deny
+/// empty-range: allow
+/// ```
+///
+/// Deserializing a configuration which mentions an unknown rule id is an error.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(try_from = "HashMap<String, Severity>")]
+pub struct LintConfig(HashMap<String, Severity>);
+
+impl TryFrom<HashMap<String, Severity>> for LintConfig {
+    type Error = VersionitisError;
+
+    fn try_from(severities: HashMap<String, Severity>) -> Result<Self, Self::Error> {
+        let mut config = LintConfig::new();
+        for (id, severity) in severities {
+            config.set(&id, severity)?;
+        }
+        Ok(config)
+    }
+}
+
+impl LintConfig {
+    /// New up a LintConfig which uses the default severities
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    /// Set the severity for a rule, given its id. Returns an UnknownLintRule
+    /// error if the id does not name a rule.
+    pub fn set(&mut self, id: &str, severity: Severity) -> Result<(), VersionitisError> {
+        if LintRule::from_id(id).is_none() {
+            return Err(VersionitisError::UnknownLintRule(id.to_string()));
+        }
+        self.0.insert(id.to_string(), severity);
+        Ok(())
+    }
+
+    /// Retrieve the effective severity for a rule
+    pub fn severity(&self, rule: LintRule) -> Severity {
+        self.0
+            .get(rule.id())
+            .cloned()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// A single lint finding
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub manifest: Option<String>,
+    pub dependency: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: ", self.severity, self.rule)?;
+        if let Some(ref manifest) = self.manifest {
+            write!(f, "{}: ", manifest)?;
+        }
+        if let Some(ref dependency) = self.dependency {
+            write!(f, "{}: ", dependency)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The collected Diagnostics from one or more lint passes
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LintReport {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    /// New up an empty LintReport
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retrieve the diagnostics
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Append the diagnostics from another report to this one
+    pub fn extend(&mut self, other: LintReport) {
+        self.diagnostics.extend(other.diagnostics);
+    }
+
+    /// True if any diagnostic has a severity of Deny
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Deny)
+    }
+
+    /// Render the report as human readable text, one diagnostic per line
+    pub fn to_text(&self) -> String {
+        self.diagnostics
+            .iter()
+            .map(|d| format!("{}\n", d))
+            .collect::<String>()
+    }
+
+    /// Render the report as json
+    pub fn to_json(&self) -> Result<String, VersionitisError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    // record a finding, unless the rule is allowed
+    fn report<M: Into<String>>(
+        &mut self,
+        config: &LintConfig,
+        rule: LintRule,
+        manifest: Option<&str>,
+        dependency: Option<&str>,
+        message: M,
+    ) {
+        let severity = config.severity(rule);
+        if severity == Severity::Allow {
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            rule: rule.id(),
+            severity,
+            manifest: manifest.map(|m| m.to_string()),
+            dependency: dependency.map(|d| d.to_string()),
+            message: message.into(),
+        });
+    }
+}
+
+/// Lint a manifest, checking its name as well as each of its dependencies.
+///
+/// # Example
+///
+/// ```ignore
+/// let report = lint_manifest(&manifest, &LintConfig::new());
+/// print!("{}", report.to_text());
+/// ```
+pub fn lint_manifest(manifest: &Manifest, config: &LintConfig) -> LintReport {
+    let mut report = LintReport::new();
+    let name = manifest.package().spec();
+    let name = name.as_str();
+    // manifests which have been deserialized always parse, but a Package
+    // may be constructed by hand with any name.
+    if PackageVersionParser::parse(name).is_err() {
+        report.report(
+            config,
+            LintRule::InvalidName,
+            Some(name),
+            None,
+            "name is not of the form name-version",
+        );
+    }
+    // optional and conditional dependencies count, as enabling a feature or
+    // matching a condition would make the manifest depend upon itself
+    let depends_on_self = Phase::ALL
+        .iter()
+        .map(|phase| manifest.dependencies_for(*phase))
+        .chain(manifest.optional().values())
+        .chain(manifest.conditional().iter().map(|c| c.dependencies()))
+        .any(|dependencies| dependencies.contains_key(manifest.name()));
+    if depends_on_self {
+        report.report(
+            config,
+            LintRule::SelfDependency,
+            Some(name),
+            Some(manifest.name()),
+            "manifest depends upon its own package",
+        );
+    }
+    for phase in Phase::ALL.iter() {
+        lint_intervals(&mut report, config, Some(name), manifest.dependencies_for(*phase));
+    }
+    report
+}
+
+/// Lint the intervals in an IntervalMap which is not associated with a Manifest
+pub fn lint_interval_map(map: &IntervalMap, config: &LintConfig) -> LintReport {
+    let mut report = LintReport::new();
+    lint_intervals(&mut report, config, None, map);
+    report
+}
+
+// Apply the interval rules to each entry in the map, in name order so
+// that output is stable.
+fn lint_intervals(
+    report: &mut LintReport,
+    config: &LintConfig,
+    manifest: Option<&str>,
+    map: &IntervalMap,
+) {
+    let mut names = map.keys().collect::<Vec<&String>>();
+    names.sort();
+    for name in names {
+        // the key came from the map, so we may unwrap
+        let interval = map.get(name).unwrap();
+        lint_interval(report, config, manifest, name, interval);
+    }
+}
+
+fn lint_interval(
+    report: &mut LintReport,
+    config: &LintConfig,
+    manifest: Option<&str>,
+    dependency: &str,
+    interval: &VersionNumberInterval,
+) {
+    let mut emit = |rule: LintRule, message: String| {
+        report.report(config, rule, manifest, Some(dependency), message)
+    };
+    match interval {
+        Interval::Single(_) => {
+            emit(LintRule::ExactPin, format!("'{}' pins an exact version", interval));
+        }
+        Interval::HalfOpen { start, end } | Interval::Open { start, end } => {
+            if start > end {
+                emit(
+                    LintRule::InvertedInterval,
+                    format!("'{}' has a start greater than its end", interval),
+                );
+            } else if start == end {
+                match interval {
+                    Interval::HalfOpen { .. } => emit(
+                        LintRule::EmptyRange,
+                        format!("'{}' contains no versions", interval),
+                    ),
+                    _ => emit(
+                        LintRule::ExactPin,
+                        format!("'{}' only contains a single version", interval),
+                    ),
+                }
+            }
+        }
+        Interval::Empty => {
+            emit(LintRule::EmptyRange, "interval is explicitly empty".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::Range;
+    use crate::package::Package;
+    use crate::version_number::VersionNumber;
+    use std::str::FromStr;
+
+    type VI = VersionNumberInterval;
+
+    fn ids(report: &LintReport) -> Vec<&'static str> {
+        report.diagnostics().iter().map(|d| d.rule).collect()
+    }
+
+    #[test]
+    fn well_formed_manifest_is_clean() {
+        let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
+        manifest
+            .add_dependency("foo", VI::from_range(&Range::HalfOpen("0.1.0", "1.0.0")).unwrap())
+            .unwrap();
+        let report = lint_manifest(&manifest, &LintConfig::new());
+        assert_eq!(report.diagnostics(), &[]);
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn flags_each_rule() {
+        let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
+        let pin = VI::from_range(&Range::Single("0.1.0")).unwrap();
+        let bounded = VI::from_range(&Range::HalfOpen("1.0.0", "65535")).unwrap();
+        let inverted = Interval::Open {
+            start: VersionNumber::semver(2, 0, 0),
+            end: VersionNumber::semver(1, 0, 0),
+        };
+        let empty = Interval::HalfOpen {
+            start: VersionNumber::semver(1, 0, 0),
+            end: VersionNumber::semver(1, 0, 0),
+        };
+        manifest.add_dependency("a", pin).unwrap();
+        manifest.add_dependency("b", bounded).unwrap();
+        manifest.add_dependency("c", inverted).unwrap();
+        manifest.add_dependency("d", empty).unwrap();
+        manifest.add_dependency("e", VI::empty()).unwrap();
+        manifest
+            .add_dependency("fred", VI::from_range(&Range::Single("0.9.0")).unwrap())
+            .unwrap();
+
+        let report = lint_manifest(&manifest, &LintConfig::new());
+        assert_eq!(
+            ids(&report),
+            vec![
+                "self-dependency",
+                "exact-pin",
+                "inverted-interval",
+                "empty-range",
+                "empty-range",
+                "exact-pin"
+            ]
+        );
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn flags_optional_and_conditional_self_dependencies() {
+        let pin = || VI::from_range(&Range::HalfOpen("0.1.0", "1.0.0")).unwrap();
+        let mut optional = Manifest::from_str("fred-1.0.0").unwrap();
+        optional.add_optional_dependency("docs", "fred", pin()).unwrap();
+        assert_eq!(ids(&lint_manifest(&optional, &LintConfig::new())), vec!["self-dependency"]);
+
+        let mut conditional = Manifest::from_str("fred-1.0.0").unwrap();
+        let when = vec![("platform".to_string(), "linux".to_string())].into_iter().collect();
+        conditional.add_conditional_dependency(when, "fred", pin()).unwrap();
+        assert_eq!(ids(&lint_manifest(&conditional, &LintConfig::new())), vec!["self-dependency"]);
+    }
+
+    #[test]
+    fn flags_invalid_name() {
+        let manifest = Manifest::new(Package::new("1fred", VersionNumber::semver(1, 0, 0)));
+        let report = lint_manifest(&manifest, &LintConfig::new());
+        assert_eq!(ids(&report), vec!["invalid-name"]);
+    }
+
+    #[test]
+    fn severities_are_configurable() {
+        let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
+        manifest
+            .add_dependency("foo", VI::from_range(&Range::Single("0.1.0")).unwrap())
+            .unwrap();
+
+        let mut config = LintConfig::new();
+        config.set("exact-pin", Severity::Allow).unwrap();
+        assert_eq!(lint_manifest(&manifest, &config).diagnostics(), &[]);
+
+        config.set("exact-pin", Severity::Deny).unwrap();
+        let report = lint_manifest(&manifest, &config);
+        assert_eq!(report.diagnostics()[0].severity, Severity::Deny);
+        assert!(report.has_errors());
+
+        assert_eq!(
+            config.set("no-such-rule", Severity::Deny),
+            Err(VersionitisError::UnknownLintRule("no-such-rule".to_string()))
+        );
+    }
+
+    #[test]
+    fn can_deserialize_config_from_yaml() {
+        let config: LintConfig = serde_yaml::from_str("exact-pin: deny\nempty-range: allow").unwrap();
+        assert_eq!(config.severity(LintRule::ExactPin), Severity::Deny);
+        assert_eq!(config.severity(LintRule::EmptyRange), Severity::Allow);
+        assert_eq!(config.severity(LintRule::SelfDependency), Severity::Deny);
+    }
+
+    #[test]
+    fn deserializing_an_unknown_rule_is_an_error() {
+        let result: Result<LintConfig, _> = serde_yaml::from_str("exact_pin: deny\nempty-range: allow");
+        assert!(result.unwrap_err().to_string().contains("UnknownLintRule: exact_pin"));
+    }
+
+    #[test]
+    fn can_render_text_and_json() {
+        let mut map = IntervalMap::new();
+        map.insert("foo", VI::from_range(&Range::Single("0.1.0")).unwrap());
+        let report = lint_interval_map(&map, &LintConfig::new());
+
+        assert_eq!(
+            report.to_text(),
+            "warn[exact-pin]: foo: '0.1.0' pins an exact version\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["diagnostics"][0]["rule"], "exact-pin");
+        assert_eq!(json["diagnostics"][0]["severity"], "warn");
+        assert_eq!(json["diagnostics"][0]["dependency"], "foo");
+    }
+
+    #[test]
+    fn severities_read_back_as_written() {
+        for severity in &[Severity::Allow, Severity::Warn, Severity::Deny] {
+            let config: LintConfig = serde_yaml::from_str(&format!("exact-pin: {}", severity)).unwrap();
+            assert_eq!(config.severity(LintRule::ExactPin), *severity);
+        }
+    }
+}
@@ -2,62 +2,449 @@
 //!
 //! stores package dependencies
 //!
-use crate::{errors::VersionitisError, interval_map::IntervalMap, package::owned::Package};
+use crate::{context::Context, errors::VersionitisError, interval_map::IntervalMap, manifest_repo::ManifestRepo, package::Package};
 use serde_derive::{Deserialize, Serialize};
 use crate::version_number_interval::{ VersionNumberInterval };
+use crate::version_number::VersionNumber;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
-/// A manifest stores a set of dependencies for a named package.
-/// The dependencies are modeled as a HashSet<Interval<Package>>.
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// The phase of a package's lifecycle in which a set of dependencies is needed.
+/// Run dependencies are serialized as `dependencies`, while the others are
+/// serialized as `build_dependencies`, `test_dependencies` and
+/// `dev_dependencies`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Build,
+    Run,
+    Test,
+    Dev,
+}
+
+impl Phase {
+    /// All of the phases
+    pub const ALL: [Phase; 4] = [Phase::Build, Phase::Run, Phase::Test, Phase::Dev];
+
+    /// The name of the manifest section holding the phase's dependencies
+    pub fn section(self) -> &'static str {
+        match self {
+            Phase::Build => "build_dependencies",
+            Phase::Run => "dependencies",
+            Phase::Test => "test_dependencies",
+            Phase::Dev => "dev_dependencies",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Build => write!(f, "build"),
+            Phase::Run => write!(f, "run"),
+            Phase::Test => write!(f, "test"),
+            Phase::Dev => write!(f, "dev"),
+        }
+    }
+}
+
+impl FromStr for Phase {
+    type Err = VersionitisError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "build" => Ok(Phase::Build),
+            "run" => Ok(Phase::Run),
+            "test" => Ok(Phase::Test),
+            "dev" => Ok(Phase::Dev),
+            _ => Err(VersionitisError::ParseError(format!("unknown phase: '{}'", s))),
+        }
+    }
+}
+
+/// A set of dependencies which only apply when every key/value pair in
+/// `when` matches a fact in the Context (eg platform: linux).
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ConditionalDependencies {
+    #[serde(serialize_with = "crate::canonical::serialize_sorted")]
+    when: HashMap<String, String>,
+    dependencies: IntervalMap,
+}
+
+impl ConditionalDependencies {
+    /// New up an empty set of dependencies, guarded by the supplied conditions
+    pub fn new(when: HashMap<String, String>) -> Self {
+        Self {
+            when,
+            dependencies: IntervalMap::new(),
+        }
+    }
+
+    /// return the conditions which must hold for the dependencies to apply
+    pub fn when(&self) -> &HashMap<String, String> {
+        &self.when
+    }
+
+    /// return the guarded dependencies
+    pub fn dependencies(&self) -> &IntervalMap {
+        &self.dependencies
+    }
+}
+
+/// A manifest stores a set of dependencies for a versioned package.
+/// The dependencies are modeled as an IntervalMap, mapping package names to
+/// VersionNumberIntervals. The package is serialized as `name`, in the
+/// form name-version (eg `name: foo-0.1.0`).
+///
+/// Dependencies are split by Phase. The `dependencies` section holds the run
+/// dependencies, and the build, test and dev dependencies each have a section of
+/// their own.
+///
+/// A manifest may also declare `conflicts`, keyed by package name, listing the
+/// versions of other packages with which it cannot coexist.
+///
+/// A manifest may declare that it `provides` versions of virtual packages
+/// (eg `provides: [colormanagement-2.1]`). A dependency on a virtual package
+/// may be satisfied by any manifest which provides a matching version.
+///
+/// In addition to its hard dependencies, a manifest may declare `optional`
+/// dependencies, keyed by feature name, and `conditional` dependencies which
+/// apply only in a matching environment:
+///
+/// ```yaml
+/// name: plugin-1.0.0
+/// dependencies:
+///   foo: '1.0.0<2.0.0'
+/// optional:
+///   gui:
+///     qt: '5.0.0<6.0.0'
+/// conditional:
+///   - when:
+///       host: maya
+///     dependencies:
+///       maya_sdk: '2019<2020'
+/// ```
+///
+/// Manifests are serialized with a `schema_version`; see the schema module.
+///
+/// Finally, a manifest may `extends` another manifest (eg `extends: foo-1.0.0`)
+/// or a named template (eg `extends: cpp_base`), inheriting any dependency,
+/// conflict or provides declaration which it does not itself override.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Manifest {
-    name: String,
+    #[serde(
+        default = "crate::schema::current_schema_version",
+        deserialize_with = "crate::schema::deserialize_schema_version"
+    )]
+    schema_version: u32,
+    #[serde(rename = "name")]
+    package: Package,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
+    // plain values precede the dependency tables, as toml requires
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    provides: Vec<Package>,
     dependencies: IntervalMap,
+    #[serde(default, skip_serializing_if = "IntervalMap::is_empty")]
+    build_dependencies: IntervalMap,
+    #[serde(default, skip_serializing_if = "IntervalMap::is_empty")]
+    test_dependencies: IntervalMap,
+    #[serde(default, skip_serializing_if = "IntervalMap::is_empty")]
+    dev_dependencies: IntervalMap,
+    #[serde(default, skip_serializing_if = "IntervalMap::is_empty")]
+    conflicts: IntervalMap,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "crate::canonical::serialize_sorted"
+    )]
+    optional: HashMap<String, IntervalMap>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conditional: Vec<ConditionalDependencies>,
 }
 
 impl Manifest {
-    /// New up a manifest given a name of type Into<String>
+    /// New up a manifest for a Package
     ///
     /// # example
     ///
     /// ```notest
-    /// let manifest = Manifest::new("rustup");
+    /// let manifest = Manifest::new(Package::from_str("rustup-1.0.0")?);
     /// ```
-    pub fn new<I>(name: I) -> Self
-    where
-        I: Into<String>,
-    {
+    pub fn new(package: Package) -> Self {
         Self {
-            name: name.into(),
+            schema_version: crate::schema::CURRENT_SCHEMA_VERSION,
+            package,
+            extends: None,
             dependencies: IntervalMap::new(),
+            build_dependencies: IntervalMap::new(),
+            test_dependencies: IntervalMap::new(),
+            dev_dependencies: IntervalMap::new(),
+            conflicts: IntervalMap::new(),
+            provides: Vec::new(),
+            optional: HashMap::new(),
+            conditional: Vec::new(),
+        }
+    }
+
+    /// return the schema version of the manifest. Manifests read from older
+    /// versions of the schema are upgraded, so this is always the current version.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// return the package which the manifest describes
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+
+    /// return the name of the package, sans version
+    pub fn name(&self) -> &str {
+        self.package.name()
+    }
+
+    /// return the version of the package
+    pub fn version_number(&self) -> &VersionNumber {
+        self.package.version_number()
+    }
+
+    /// return the manifest or template which the manifest extends, if any
+    pub fn extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+
+    /// Set the manifest or template which the manifest extends. A parent which
+    /// parses as a package (eg foo-1.0.0) names a manifest, otherwise it names
+    /// a template.
+    pub fn set_extends<I: Into<String>>(&mut self, parent: Option<I>) {
+        self.extends = parent.map(|parent| parent.into());
+    }
+
+    /// Merge the declarations of a parent manifest into this one. Each
+    /// dependency, conflict, provides and feature entry of the parent is
+    /// inherited unless the manifest already declares one for the same package
+    /// name. Conditional dependencies are merged by their conditions. The
+    /// manifest's package and `extends` are left untouched.
+    pub fn inherit(&mut self, parent: &Manifest) {
+        fn merge(child: &mut IntervalMap, parent: &IntervalMap) {
+            for (name, interval) in parent.iter() {
+                if !child.contains_key(name.as_str()) {
+                    child.insert(name.clone(), interval.clone());
+                }
+            }
+        }
+
+        for phase in Phase::ALL.iter() {
+            merge(self.dependencies_for_mut(*phase), parent.dependencies_for(*phase));
+        }
+        merge(&mut self.conflicts, &parent.conflicts);
+        for provided in &parent.provides {
+            if !self.provides_name(provided.name()) {
+                self.provides.push(*provided);
+            }
+        }
+        for (feature, dependencies) in &parent.optional {
+            merge(self.optional.entry(feature.clone()).or_default(), dependencies);
+        }
+        for conditional in &parent.conditional {
+            match self.conditional.iter_mut().find(|c| c.when == conditional.when) {
+                Some(existing) => merge(&mut existing.dependencies, &conditional.dependencies),
+                None => self.conditional.push(conditional.clone()),
+            }
         }
     }
 
-    /// return the name the package
-    pub fn package(&self) -> &str {
-        return self.name.as_str();
+    /// return the run dependencies of the package
+    pub fn dependencies(&self) -> &IntervalMap {
+        &self.dependencies
     }
+
+    /// return the dependencies of the package for a particular phase
+    pub fn dependencies_for(&self, phase: Phase) -> &IntervalMap {
+        match phase {
+            Phase::Build => &self.build_dependencies,
+            Phase::Run => &self.dependencies,
+            Phase::Test => &self.test_dependencies,
+            Phase::Dev => &self.dev_dependencies,
+        }
+    }
+
+    fn dependencies_for_mut(&mut self, phase: Phase) -> &mut IntervalMap {
+        match phase {
+            Phase::Build => &mut self.build_dependencies,
+            Phase::Run => &mut self.dependencies,
+            Phase::Test => &mut self.test_dependencies,
+            Phase::Dev => &mut self.dev_dependencies,
+        }
+    }
+
     /// Add a dependency to the manifest
     ///
     /// # example
     ///
     /// ```ignore
-    /// let manifest = Manifest::new("coolpackage");
+    /// let manifest = Manifest::from_str("coolpackage-1.0.0")?;
     /// let interval = halfopen_from_strs("bar-0.1.0", "bar-1.0.0")?;
     /// manifest.add_dependency(interval)?;
     /// ```
     pub fn add_dependency<I: Into<String>>(&mut self, package_name: I, interval: VersionNumberInterval) -> Result<(), VersionitisError> {
+        self.add_dependency_for(Phase::Run, package_name, interval)
+    }
+
+    /// Add a dependency to the manifest for a particular phase. Returns an
+    /// error if the phase already depends on the package.
+    pub fn add_dependency_for<I: Into<String>>(&mut self, phase: Phase, package_name: I, interval: VersionNumberInterval) -> Result<(), VersionitisError> {
         let package_name = package_name.into();
-        if self.depends_on(package_name.as_str()) {
+        let dependencies = self.dependencies_for_mut(phase);
+        if dependencies.contains_key(package_name.as_str()) {
             return Err(VersionitisError::DuplicatePackageDependency(
                 package_name
             ));
         }
-        self.dependencies.insert(package_name, interval);
+        dependencies.insert(package_name, interval);
+        Ok(())
+    }
+
+    /// return the conflicts, mapping package names to the intervals of versions
+    /// with which the manifest's package cannot coexist
+    pub fn conflicts(&self) -> &IntervalMap {
+        &self.conflicts
+    }
+
+    /// Declare that the manifest's package cannot coexist with the versions of
+    /// a package contained in the interval. Returns an error if a conflict with
+    /// the package has already been declared.
+    pub fn add_conflict<I: Into<String>>(&mut self, package_name: I, interval: VersionNumberInterval) -> Result<(), VersionitisError> {
+        let package_name = package_name.into();
+        if self.conflicts.contains_key(package_name.as_str()) {
+            return Err(VersionitisError::DuplicatePackageConflict(package_name));
+        }
+        self.conflicts.insert(package_name, interval);
+        Ok(())
+    }
+
+    /// Test whether the manifest declares a conflict with a particular
+    /// versioned package.
+    pub fn conflicts_with(&self, package: &Package) -> bool {
+        match self.conflicts.get(package.name()) {
+            Some(interval) => interval.contains(package.version_number()),
+            None => false,
+        }
+    }
+
+    /// Test whether two manifests may coexist. This is the case unless either
+    /// declares a conflict with the other's package.
+    pub fn is_compatible_with(&self, other: &Manifest) -> bool {
+        !self.conflicts_with(other.package()) && !other.conflicts_with(self.package())
+    }
+
+    /// return the virtual packages which the manifest provides
+    pub fn provides(&self) -> &[Package] {
+        &self.provides
+    }
+
+    /// Declare that the manifest's package provides a version of a virtual
+    /// package. Returns an error if a version of the virtual package has
+    /// already been declared.
+    pub fn add_provides(&mut self, package: Package) -> Result<(), VersionitisError> {
+        if self.provides_name(package.name()) {
+            return Err(VersionitisError::DuplicateProvides(package.name().to_string()));
+        }
+        self.provides.push(package);
+        Ok(())
+    }
+
+    /// Test whether the manifest provides any version of the named virtual package
+    pub fn provides_name(&self, name: &str) -> bool {
+        self.provides.iter().any(|p| p.name() == name)
+    }
+
+    /// return the optional dependencies, keyed by feature name
+    pub fn optional(&self) -> &HashMap<String, IntervalMap> {
+        &self.optional
+    }
+
+    /// return the conditional dependencies
+    pub fn conditional(&self) -> &[ConditionalDependencies] {
+        &self.conditional
+    }
+
+    /// Add a dependency which is only required when `feature` is requested.
+    /// Returns an error if the feature already depends on the package.
+    pub fn add_optional_dependency<F, I>(&mut self, feature: F, package_name: I, interval: VersionNumberInterval) -> Result<(), VersionitisError>
+    where
+        F: Into<String>,
+        I: Into<String>,
+    {
+        let package_name = package_name.into();
+        let dependencies = self.optional.entry(feature.into()).or_default();
+        if dependencies.contains_key(package_name.as_str()) {
+            return Err(VersionitisError::DuplicatePackageDependency(package_name));
+        }
+        dependencies.insert(package_name, interval);
+        Ok(())
+    }
+
+    /// Add a dependency which is only required when every key/value pair
+    /// in `when` matches a fact in the Context. Dependencies sharing the same
+    /// conditions are grouped together.
+    pub fn add_conditional_dependency<I: Into<String>>(&mut self, when: HashMap<String, String>, package_name: I, interval: VersionNumberInterval) -> Result<(), VersionitisError> {
+        let package_name = package_name.into();
+        let idx = match self.conditional.iter().position(|c| c.when == when) {
+            Some(idx) => idx,
+            None => {
+                self.conditional.push(ConditionalDependencies::new(when));
+                self.conditional.len() - 1
+            }
+        };
+        let dependencies = &mut self.conditional[idx].dependencies;
+        if dependencies.contains_key(package_name.as_str()) {
+            return Err(VersionitisError::DuplicatePackageDependency(package_name));
+        }
+        dependencies.insert(package_name, interval);
         Ok(())
     }
 
+    /// Expand the hard, optional, and conditional run dependencies into the concrete
+    /// requirements for a Context. Optional dependencies are included for each
+    /// requested feature, and conditional dependencies whose conditions match the
+    /// Context's facts. When a package is required more than once, the
+    /// intervals are intersected, which may leave an empty interval.
+    pub fn requirements(&self, context: &Context) -> IntervalMap {
+        let mut requirements = IntervalMap::new();
+        let mut add_all = |dependencies: &IntervalMap| {
+            for (name, interval) in dependencies.iter() {
+                requirements.insert_intersection(name.as_str(), interval.clone());
+            }
+        };
+        add_all(&self.dependencies);
+        for (feature, dependencies) in &self.optional {
+            if context.has_feature(feature) {
+                add_all(dependencies);
+            }
+        }
+        for conditional in &self.conditional {
+            if context.satisfies(&conditional.when) {
+                add_all(&conditional.dependencies);
+            }
+        }
+        requirements
+    }
+
+    /// Expand the dependencies for a phase into concrete requirements. Optional
+    /// and conditional dependencies are run dependencies, so only the Run
+    /// phase takes the Context into account.
+    pub fn requirements_for(&self, phase: Phase, context: &Context) -> IntervalMap {
+        match phase {
+            Phase::Run => self.requirements(context),
+            _ => self.dependencies_for(phase).clone(),
+        }
+    }
+
     /// Test whether a manifest has a package as a dependency. This method is
-    /// only concerned with a package name. It will match any version
+    /// only concerned with a package name. It will match any version. Only hard
+    /// run dependencies are considered; see `requirements` for the rest.
     pub fn depends_on(&self, name: &str) -> bool {
         self.dependencies.contains_key(name)
         // for (package, _dep) in self.dependencies.iter() {
@@ -70,18 +457,69 @@ impl Manifest {
 
     /// Test whether a manifest has a particular versioned package as a
     /// dependency. For intervals, this means that the Package is contained within.
+    /// The package may be virtual (eg colormanagement-2.1), in which case the
+    /// manifest must depend on the virtual name.
+    ///
+    /// A Package carries no record of the virtual packages it provides, so
+    /// `depends_on_package(ocio_impl-1.0.0)` is false for a manifest depending
+    /// on colormanagement. Use `depends_on_package_in` to look up what a
+    /// package provides in a ManifestRepo, or `depends_on_provider` given the
+    /// package's manifest.
     pub fn depends_on_package(&self, package: &Package) -> bool {
         if let Some(dep) = self.dependencies.get(package.name()){
             return dep.contains(package.version_number())
         };
         false
     }
+
+    /// Test whether a manifest has a particular versioned package as a
+    /// dependency, either directly or through one of the virtual packages
+    /// which the package's manifest in `repo` provides. A package without a
+    /// manifest in `repo` is tested as `depends_on_package` would.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // ocio_impl-1.0.0 provides colormanagement-2.1
+    /// assert!(app.depends_on_package_in(&Package::from_str("ocio_impl-1.0.0")?, &repo));
+    /// ```
+    pub fn depends_on_package_in(&self, package: &Package, repo: &ManifestRepo) -> bool {
+        match repo.get_package(package) {
+            Some(provider) => self.depends_on_provider(provider),
+            None => self.depends_on_package(package),
+        }
+    }
+
+    /// Test whether a manifest's run dependencies may be satisfied by another
+    /// manifest, either directly or through one of the virtual packages which
+    /// the other manifest provides. This is the provider aware counterpart of
+    /// `depends_on_package`.
+    pub fn depends_on_provider(&self, provider: &Manifest) -> bool {
+        self.depends_on_package(provider.package())
+            || provider.provides().iter().any(|p| self.depends_on_package(p))
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = VersionitisError;
+
+    /// New up a manifest given a str of the form name-version, returning
+    /// an error if the str does not parse.
+    ///
+    /// # example
+    ///
+    /// ```notest
+    /// let manifest = Manifest::from_str("rustup-1.0.0")?;
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(Package::from_str(name)?))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::interval::Range;
+    use crate::{interval::Range, manifest_repo::ManifestArena};
 
     mod manifest {
         use super::*;
@@ -90,21 +528,28 @@ mod tests {
         fn can_retrieve_package_name_version() {
             type VI = VersionNumberInterval;
             use self::Range::*;
-            let mut manifest = Manifest::new("fred-1.0.0");
+            let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
             let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
             let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
             manifest.add_dependency("foo", interval1).unwrap();
             manifest.add_dependency("bar", interval2).unwrap();
 
-            let name = manifest.package();
-            assert_eq!(name, "fred-1.0.0");
+            let package = manifest.package();
+            assert_eq!(package, &Package::from_str("fred-1.0.0").unwrap());
+            assert_eq!(manifest.name(), "fred");
+            assert_eq!(manifest.version_number(), &VersionNumber::semver(1, 0, 0));
+        }
+
+        #[test]
+        fn cannot_create_manifest_from_malformed_name() {
+            assert!(Manifest::from_str("fred").is_err());
         }
 
         #[test]
         fn can_add_dependencies() {
             type VI = VersionNumberInterval;
             use self::Range::*;
-            let mut manifest = Manifest::new("fred-1.0.0");
+            let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
             let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
             let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
             manifest.add_dependency("foo", interval1).unwrap();
@@ -116,7 +561,7 @@ mod tests {
         fn cannot_add_duplicate_dependency() {
             type VI = VersionNumberInterval;
             use self::Range::*;
-            let mut manifest = Manifest::new("fred-1.0.0");
+            let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
             let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
             let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
             let interval3 = VI::from_range(&Open("1.1.0", "2.0.0")).unwrap();
@@ -134,7 +579,7 @@ mod tests {
         fn can_identify_dependency_from_package_name_using_depends_on() {
             type VI = VersionNumberInterval;
             use self::Range::*;
-            let mut manifest = Manifest::new("fred-1.0.0");
+            let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
             let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
             let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
             let interval3 = VI::from_range(&Open("0.1.0", "1.0.0")).unwrap();
@@ -152,7 +597,7 @@ mod tests {
             let pfs = |n: &str| Package::from_str(n).unwrap();
             type VI = VersionNumberInterval;
             use self::Range::*;
-            let mut manifest = Manifest::new("fred-1.0.0");
+            let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
             let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
             let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
             let interval3 = VI::from_range(&Open("0.1.0", "1.0.0")).unwrap();
@@ -175,6 +620,201 @@ mod tests {
             assert!(!manifest.depends_on_package(&pfs("blargybalargy-1.0.0")));
         }
 
+        fn when(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        }
+
+        fn setup_plugin() -> Manifest {
+            type VI = VersionNumberInterval;
+            use self::Range::*;
+            let mut manifest = Manifest::from_str("plugin-1.0.0").unwrap();
+            manifest.add_dependency("foo", VI::from_range(&HalfOpen("1.0.0", "2.0.0")).unwrap()).unwrap();
+            manifest.add_optional_dependency("gui", "qt", VI::from_range(&HalfOpen("5.0.0", "6.0.0")).unwrap()).unwrap();
+            manifest.add_conditional_dependency(when(&[("host", "maya")]), "maya_sdk", VI::from_range(&Single("2019")).unwrap()).unwrap();
+            manifest.add_conditional_dependency(when(&[("host", "maya")]), "foo", VI::from_range(&Open("1.5.0", "3.0.0")).unwrap()).unwrap();
+            manifest.add_conditional_dependency(when(&[("host", "houdini")]), "hdk", VI::from_range(&Single("17.5")).unwrap()).unwrap();
+            manifest
+        }
+
+        #[test]
+        fn requirements_without_context_are_hard_dependencies() {
+            let manifest = setup_plugin();
+            let requirements = manifest.requirements(&Context::new());
+            assert_eq!(&requirements, manifest.dependencies());
+            assert_eq!(manifest.conditional().len(), 2);
+        }
+
+        #[test]
+        fn requirements_include_requested_features() {
+            let manifest = setup_plugin();
+            let mut context = Context::new();
+            context.add_feature("gui");
+            let requirements = manifest.requirements(&context);
+            assert_eq!(requirements.len(), 2);
+            assert_eq!(requirements.get("qt").unwrap().to_string(), "5.0.0<6.0.0");
+        }
+
+        #[test]
+        fn requirements_include_matching_conditions_and_intersect() {
+            let manifest = setup_plugin();
+            let mut context = Context::new();
+            context.set_fact("host", "maya");
+            let requirements = manifest.requirements(&context);
+            assert_eq!(requirements.len(), 2);
+            assert_eq!(requirements.get("maya_sdk").unwrap().to_string(), "2019");
+            assert_eq!(requirements.get("foo").unwrap().to_string(), "1.5.0<2.0.0");
+            assert!(!requirements.contains_key("hdk"));
+        }
+
+        #[test]
+        fn cannot_add_duplicate_optional_dependency() {
+            type VI = VersionNumberInterval;
+            let mut manifest = setup_plugin();
+            let result = manifest.add_optional_dependency("gui", "qt", VI::from_range(&Range::Single("5.1.0")).unwrap());
+            assert!(result.is_err());
+        }
+
+        const PLUGIN: &'static str = r#"---
+name: plugin-1.0.0
+dependencies:
+  foo: '1.0.0<2.0.0'
+optional:
+  gui:
+    qt: '5.0.0<6.0.0'
+conditional:
+  - when:
+      host: maya
+    dependencies:
+      maya_sdk: '2019'
+      foo: '1.5.0<=3.0.0'
+  - when:
+      host: houdini
+    dependencies:
+      hdk: '17.5'"#;
+
+        #[test]
+        fn can_deserialize_optional_and_conditional_dependencies() {
+            let manifest: Manifest = serde_yaml::from_str(PLUGIN).unwrap();
+            assert_eq!(manifest, setup_plugin());
+        }
+
+        #[test]
+        fn can_round_trip_optional_and_conditional_dependencies() {
+            let manifest = setup_plugin();
+            let result = serde_yaml::to_string(&manifest).unwrap();
+            let expected: Manifest = serde_yaml::from_str(&result).unwrap();
+            assert_eq!(manifest, expected);
+        }
+
+        #[test]
+        fn can_declare_provides() {
+            type VI = VersionNumberInterval;
+            use self::Range::*;
+            let yaml = "name: ocio_impl-1.0.0\ndependencies: {}\nprovides:\n  - colormanagement-2.1\n";
+            let provider: Manifest = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(provider.provides(), &[Package::from_str("colormanagement-2.1").unwrap()]);
+            assert!(provider.provides_name("colormanagement"));
+
+            let mut manifest = Manifest::from_str("app-1.0.0").unwrap();
+            manifest.add_dependency("colormanagement", VI::from_range(&HalfOpen("2.0", "3.0")).unwrap()).unwrap();
+            assert!(manifest.depends_on_package(&Package::from_str("colormanagement-2.1").unwrap()));
+            assert!(manifest.depends_on_provider(&provider));
+            assert!(!manifest.depends_on_provider(&Manifest::from_str("other-1.0.0").unwrap()));
+            // a provider's own package says nothing of what it provides
+            assert!(!manifest.depends_on_package(provider.package()));
+            // but the provider index of a ManifestRepo does
+            let arena = ManifestArena::new();
+            let mut repo = ManifestRepo::new(&arena);
+            repo.add(provider.clone());
+            assert!(manifest.depends_on_package_in(provider.package(), &repo));
+            assert!(manifest.depends_on_package_in(&Package::from_str("colormanagement-2.1").unwrap(), &repo));
+            assert!(!manifest.depends_on_package_in(&Package::from_str("other-1.0.0").unwrap(), &repo));
+
+            let mut provider = Manifest::from_str("ocio_impl-1.0.0").unwrap();
+            provider.add_provides(Package::from_str("colormanagement-2.1").unwrap()).unwrap();
+            assert!(provider.add_provides(Package::from_str("colormanagement-2.2").unwrap()).is_err());
+        }
+
+        #[test]
+        fn can_add_dependencies_by_phase() {
+            type VI = VersionNumberInterval;
+            use self::Range::*;
+            let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
+            manifest.add_dependency("foo", VI::from_range(&Single("0.1.0")).unwrap()).unwrap();
+            manifest.add_dependency_for(Phase::Build, "cmake", VI::from_range(&HalfOpen("3.0.0", "4.0.0")).unwrap()).unwrap();
+            manifest.add_dependency_for(Phase::Test, "foo", VI::from_range(&Single("0.1.0")).unwrap()).unwrap();
+            assert!(manifest.add_dependency_for(Phase::Build, "cmake", VI::from_range(&Single("3.1.0")).unwrap()).is_err());
+
+            assert_eq!(manifest.dependencies_for(Phase::Run).len(), 1);
+            assert_eq!(manifest.dependencies_for(Phase::Build).len(), 1);
+            assert_eq!(manifest.dependencies_for(Phase::Test).len(), 1);
+            assert!(manifest.dependencies_for(Phase::Dev).is_empty());
+            assert!(!manifest.depends_on("cmake"));
+        }
+
+        const PHASES: &'static str = r#"---
+name: fred-1.0.0
+dependencies:
+  foo: '0.1.0'
+build_dependencies:
+  cmake: '3.0.0<4.0.0'
+dev_dependencies:
+  gdb: '8.0'"#;
+
+        #[test]
+        fn can_deserialize_phases_from_yaml() {
+            let manifest: Manifest = serde_yaml::from_str(PHASES).unwrap();
+            assert_eq!(manifest.dependencies_for(Phase::Build).get("cmake").unwrap().to_string(), "3.0.0<4.0.0");
+            assert_eq!(manifest.dependencies_for(Phase::Dev).get("gdb").unwrap().to_string(), "8.0");
+            assert!(manifest.dependencies_for(Phase::Test).is_empty());
+
+            let result = serde_yaml::to_string(&manifest).unwrap();
+            assert!(!result.contains("test_dependencies"));
+            let expected: Manifest = serde_yaml::from_str(&result).unwrap();
+            assert_eq!(manifest, expected);
+        }
+
+        const CONFLICTS: &'static str = r#"---
+name: plugin-1.0.0
+dependencies:
+  foo: '1.0.0<2.0.0'
+conflicts:
+  maya: '2019.0<2019.2'"#;
+
+        #[test]
+        fn can_round_trip_conflicts() {
+            let manifest: Manifest = serde_yaml::from_str(CONFLICTS).unwrap();
+            assert_eq!(manifest.conflicts().get("maya").unwrap().to_string(), "2019.0<2019.2");
+            let result = serde_yaml::to_string(&manifest).unwrap();
+            let expected: Manifest = serde_yaml::from_str(&result).unwrap();
+            assert_eq!(manifest, expected);
+        }
+
+        #[test]
+        fn can_identify_conflicts() {
+            let pfs = |n: &str| Package::from_str(n).unwrap();
+            let mut plugin = Manifest::from_str("plugin-1.0.0").unwrap();
+            plugin.add_conflict("maya", VersionNumberInterval::from_str("2019.0<2019.2").unwrap()).unwrap();
+            assert!(plugin.add_conflict("maya", VersionNumberInterval::from_str("2018").unwrap()).is_err());
+
+            assert!(plugin.conflicts_with(&pfs("maya-2019.1")));
+            assert!(!plugin.conflicts_with(&pfs("maya-2019.2")));
+            assert!(!plugin.conflicts_with(&pfs("houdini-2019.1")));
+
+            let maya = Manifest::from_str("maya-2019.1").unwrap();
+            assert!(!plugin.is_compatible_with(&maya));
+            assert!(!maya.is_compatible_with(&plugin));
+            assert!(plugin.is_compatible_with(&Manifest::from_str("maya-2020.0").unwrap()));
+        }
+
+        #[test]
+        fn can_parse_phase() {
+            for phase in Phase::ALL.iter() {
+                assert_eq!(Phase::from_str(&phase.to_string()), Ok(*phase));
+            }
+            assert!(Phase::from_str("deploy").is_err());
+        }
+
 /*
         const MANIFEST: &'static str = r#"---
 name: fred-1.0.0
@@ -204,7 +844,7 @@ dependencies:
                     let e_conv: VersionitisError = e.into();
                     assert_eq!(e_conv, VersionitisError::UnknownPackage("foo".to_string()))},
                 Ok(s) => {
-                    let mut manifest = Manifest::new("fred-1.0.0");
+                    let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
                     let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
                     let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
                     let interval3 = VI::from_range(&Open("0.1.0", "1.0.0")).unwrap();
@@ -221,7 +861,7 @@ dependencies:
         fn can_serialize_the_manifest_to_yaml() {
             type VI = VersionNumberInterval;
             use self::Range::*;
-            let mut manifest = Manifest::new("fred-1.0.0");
+            let mut manifest = Manifest::from_str("fred-1.0.0").unwrap();
             let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
             let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
             let interval3 = VI::from_range(&Open("0.1.0", "1.0.0")).unwrap();
@@ -0,0 +1,302 @@
+//! manifest_builder.rs
+//!
+//! Fluent construction of Manifests from strs, collecting every error
+//! along the way rather than stopping at the first.
+//!
+use crate::{
+    consistency::{self, ConsistencyIssue},
+    errors::VersionitisError,
+    manifest::{Manifest, Phase},
+    manifest_repo::ManifestRepo,
+    package::Package,
+    package_repo::PackageRepo,
+    version_number_interval::VersionNumberInterval,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Build a Manifest from strs. Each call parses its arguments, recording any
+/// error and carrying on, so that `build` can report every problem at once.
+///
+/// # Example
+///
+/// ```
+/// use versionitis::manifest_builder::ManifestBuilder;
+/// let manifest = ManifestBuilder::new("fred-1.0.0")
+///     .requires("foo", "0.1.0<1.0.0")
+///     .requires("bar", "0.2.0")
+///     .build()
+///     .unwrap();
+/// assert!(manifest.depends_on("foo"));
+/// ```
+pub struct ManifestBuilder<'r> {
+    name: String,
+    manifest: Option<Manifest>,
+    errors: Vec<VersionitisError>,
+    repo: Option<&'r PackageRepo>,
+    manifests: Option<&'r ManifestRepo<'r, 'r>>,
+}
+
+impl<'r> ManifestBuilder<'r> {
+    /// New up a builder for a package, given a str of the form name-version
+    pub fn new(package: &str) -> Self {
+        let mut errors = Vec::new();
+        let manifest = match Manifest::from_str(package) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        };
+        Self {
+            name: package.to_string(),
+            manifest,
+            errors,
+            repo: None,
+            manifests: None,
+        }
+    }
+
+    /// Add a run dependency on the versions of a package within an interval
+    /// (eg "0.1.0<1.0.0")
+    pub fn requires(self, package_name: &str, interval: &str) -> Self {
+        self.requires_for(Phase::Run, package_name, interval)
+    }
+
+    /// Add a dependency for a particular phase
+    pub fn requires_for(self, phase: Phase, package_name: &str, interval: &str) -> Self {
+        self.apply(interval, |manifest, interval| {
+            manifest.add_dependency_for(phase, package_name, interval)
+        })
+    }
+
+    /// Declare a conflict with the versions of a package within an interval
+    pub fn conflicts(self, package_name: &str, interval: &str) -> Self {
+        self.apply(interval, |manifest, interval| manifest.add_conflict(package_name, interval))
+    }
+
+    /// Add a dependency which is only required when `feature` is requested
+    pub fn optional(self, feature: &str, package_name: &str, interval: &str) -> Self {
+        self.apply(interval, |manifest, interval| {
+            manifest.add_optional_dependency(feature, package_name, interval)
+        })
+    }
+
+    /// Add a dependency which is only required when every key/value pair in
+    /// `when` matches a fact in the Context
+    pub fn when(self, when: &[(&str, &str)], package_name: &str, interval: &str) -> Self {
+        let when = when
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<String, String>>();
+        self.apply(interval, |manifest, interval| {
+            manifest.add_conditional_dependency(when, package_name, interval)
+        })
+    }
+
+    /// Declare that the package provides a version of a virtual package
+    /// (eg "colormanagement-2.1")
+    pub fn provides(mut self, package: &str) -> Self {
+        let result = Package::from_str(package).and_then(|package| match self.manifest.as_mut() {
+            Some(manifest) => manifest.add_provides(package),
+            None => Ok(()),
+        });
+        if let Err(e) = result {
+            self.errors.push(e);
+        }
+        self
+    }
+
+    /// Extend another manifest (eg "fred-0.9.0") or a named template
+    pub fn extends(mut self, parent: &str) -> Self {
+        if let Some(manifest) = self.manifest.as_mut() {
+            manifest.set_extends(Some(parent));
+        }
+        self
+    }
+
+    /// Validate dependencies and conflicts against the released versions in a
+    /// PackageRepo when `build` is called. The PackageRepo knows nothing of
+    /// virtual packages, so a dependency on one is reported as an unknown
+    /// package unless `providers_from` supplies the manifests providing it.
+    pub fn validate_against(mut self, repo: &'r PackageRepo) -> Self {
+        self.repo = Some(repo);
+        self
+    }
+
+    /// Validate dependencies on virtual packages against the versions provided
+    /// by the released manifests in a ManifestRepo, as `consistency::check` does.
+    /// Only used alongside `validate_against`.
+    pub fn providers_from(mut self, manifests: &'r ManifestRepo<'r, 'r>) -> Self {
+        self.manifests = Some(manifests);
+        self
+    }
+
+    /// Retrieve the errors collected so far. Errors found by validating against
+    /// a PackageRepo are only collected by `build`.
+    pub fn errors(&self) -> &[VersionitisError] {
+        &self.errors
+    }
+
+    /// Build the Manifest, or return an InvalidManifest error listing every
+    /// problem encountered. If a PackageRepo has been supplied, every dependency
+    /// must name a package in the repo, or a virtual package provided by a
+    /// manifest supplied via `providers_from`, and match at least one of its
+    /// released versions.
+    pub fn build(mut self) -> Result<Manifest, VersionitisError> {
+        if let (Some(manifest), Some(repo)) = (self.manifest.as_ref(), self.repo) {
+            let errors = validate(manifest, repo, self.manifests);
+            self.errors.extend(errors);
+        }
+        match self.manifest {
+            Some(manifest) if self.errors.is_empty() => Ok(manifest),
+            _ => Err(VersionitisError::InvalidManifest(format!(
+                "{}: {}",
+                self.name,
+                self.errors.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("; ")
+            ))),
+        }
+    }
+
+    // parse an interval and hand it to `add`, recording any error
+    fn apply<F>(mut self, interval: &str, add: F) -> Self
+    where
+        F: FnOnce(&mut Manifest, VersionNumberInterval) -> Result<(), VersionitisError>,
+    {
+        let result = VersionNumberInterval::from_str(interval).and_then(|interval| {
+            match self.manifest.as_mut() {
+                Some(manifest) => add(manifest, interval),
+                None => Ok(()),
+            }
+        });
+        if let Err(e) = result {
+            self.errors.push(e);
+        }
+        self
+    }
+}
+
+// check each of the manifest's dependencies and conflicts against the repo,
+// using the same checks as the consistency report
+fn validate(manifest: &Manifest, repo: &PackageRepo, manifests: Option<&ManifestRepo>) -> Vec<VersionitisError> {
+    let mut dependencies = Phase::ALL
+        .iter()
+        .flat_map(|phase| manifest.dependencies_for(*phase).iter())
+        .chain(manifest.optional().values().flat_map(|deps| deps.iter()))
+        .chain(manifest.conditional().iter().flat_map(|c| c.dependencies().iter()))
+        .collect::<Vec<_>>();
+    dependencies.sort_by(|a, b| a.0.cmp(b.0));
+    let mut conflicts = manifest.conflicts().keys().collect::<Vec<&String>>();
+    conflicts.sort();
+
+    dependencies
+        .into_iter()
+        .filter_map(|(name, interval)| consistency::check_dependency(repo, manifests, manifest, name, interval))
+        .chain(conflicts.into_iter().filter_map(|name| consistency::check_conflict(repo, manifest, name)))
+        .map(|issue| match issue {
+            ConsistencyIssue::UnknownDependency { dependency, .. } => VersionitisError::UnknownPackage(dependency),
+            ConsistencyIssue::UnknownConflict { conflict, .. } => VersionitisError::UnknownPackage(conflict),
+            ConsistencyIssue::UnsatisfiableDependency { dependency, interval, .. } => {
+                VersionitisError::UnresolvedDependency(format!(
+                    "{}: '{}' matches no released version",
+                    dependency, interval
+                ))
+            }
+            issue => VersionitisError::ConflictingDependency(issue.to_string()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::TrackPackages;
+
+    #[test]
+    fn can_build_a_manifest() {
+        let manifest = ManifestBuilder::new("fred-1.0.0")
+            .requires("foo", "0.1.0<1.0.0")
+            .requires_for(Phase::Build, "cmake", "3.14")
+            .conflicts("bar", "0.1.0<=0.2.0")
+            .optional("gui", "qt", "5.0.0<6.0.0")
+            .when(&[("host", "maya")], "maya_sdk", "2019")
+            .provides("colormanagement-2.1")
+            .build()
+            .unwrap();
+        assert!(manifest.depends_on("foo"));
+        assert!(manifest.dependencies_for(Phase::Build).contains_key("cmake"));
+        assert!(manifest.conflicts_with(&Package::from_str("bar-0.2.0").unwrap()));
+        assert!(manifest.optional().contains_key("gui"));
+        assert_eq!(manifest.conditional().len(), 1);
+        assert!(manifest.provides_name("colormanagement"));
+    }
+
+    #[test]
+    fn collects_every_error() {
+        let builder = ManifestBuilder::new("fred-1.0.0")
+            .requires("foo", "0.1.0<<1.0.0")
+            .requires("bar", "0.1.0")
+            .requires("bar", "0.2.0")
+            .provides("colormanagement");
+        assert_eq!(builder.errors().len(), 3);
+        assert!(builder.build().is_err());
+
+        let builder = ManifestBuilder::new("fred").requires("foo", "nope");
+        assert_eq!(builder.errors().len(), 2);
+    }
+
+    #[test]
+    fn validates_against_package_repo() {
+        let mut repo = PackageRepo::new();
+        repo.add_version("foo", "0.1.0").unwrap();
+        repo.add_version("bar", "0.1.0").unwrap();
+
+        let result = ManifestBuilder::new("fred-1.0.0")
+            .requires("foo", "0.1.0<1.0.0")
+            .validate_against(&repo)
+            .build();
+        assert!(result.is_ok());
+
+        let result = ManifestBuilder::new("fred-1.0.0")
+            .requires("foo", "0.2.0<1.0.0")
+            .requires("nope", "0.1.0")
+            .conflicts("bar", "0.1.0")
+            .validate_against(&repo)
+            .build();
+        assert_eq!(
+            result,
+            Err(VersionitisError::InvalidManifest(
+                "fred-1.0.0: UnresolvedDependency: foo: '0.2.0<1.0.0' matches no released version; UnknownPackage: nope"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn validates_virtual_dependencies_against_providers() {
+        use crate::manifest_repo::ManifestArena;
+
+        let mut repo = PackageRepo::new();
+        repo.add_version("ocio_impl", "1.0.0").unwrap();
+        let arena = ManifestArena::new();
+        let mut manifests = ManifestRepo::new(&arena);
+        manifests.add(ManifestBuilder::new("ocio_impl-1.0.0").provides("colormanagement-2.1").build().unwrap());
+
+        let builder = || ManifestBuilder::new("fred-1.0.0").requires("colormanagement", "2.0<3.0");
+        assert_eq!(
+            builder().validate_against(&repo).build(),
+            Err(VersionitisError::InvalidManifest("fred-1.0.0: UnknownPackage: colormanagement".to_string()))
+        );
+        assert!(builder().validate_against(&repo).providers_from(&manifests).build().is_ok());
+        assert_eq!(
+            ManifestBuilder::new("fred-1.0.0")
+                .requires("colormanagement", "3.0")
+                .validate_against(&repo)
+                .providers_from(&manifests)
+                .build(),
+            Err(VersionitisError::InvalidManifest(
+                "fred-1.0.0: UnresolvedDependency: colormanagement: '3.0' matches no released version".to_string()
+            ))
+        );
+    }
+}
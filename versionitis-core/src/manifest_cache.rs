@@ -0,0 +1,387 @@
+//! manifest_cache.rs
+//!
+//! Cache the manifests of a directory, reloading only the files which have
+//! been added, modified or deleted since the last load.
+//!
+use crate::{
+    errors::VersionitisError,
+    format::Format,
+    inheritance::{ManifestResolver, TEMPLATE_DIR},
+    manifest::Manifest,
+    manifest_repo::{ManifestArena, ManifestRepo},
+    package::Package,
+    snapshot::{source_paths, SourceFile},
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// The parsed contents of a file in the manifest directory
+#[derive(Debug, Clone)]
+enum Entry {
+    Manifest(Box<Manifest>),
+    Template(String, serde_yaml::Value),
+}
+
+#[derive(Debug, Clone)]
+struct CachedFile {
+    source: SourceFile,
+    entry: Entry,
+}
+
+// The files which changed since the last load
+#[derive(Default)]
+struct Scan {
+    updates: Vec<(PathBuf, CachedFile)>,
+    failures: Vec<(PathBuf, SourceFile, VersionitisError)>,
+    removed: Vec<PathBuf>,
+}
+
+impl Scan {
+    // test whether any file was updated or removed
+    fn is_empty(&self) -> bool {
+        self.updates.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The difference between two loads of a manifest directory, in terms of
+/// effective manifests. Each list is sorted by package.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct ManifestDiff {
+    pub added: Vec<Manifest>,
+    /// pairs of (old, new) manifests
+    pub modified: Vec<(Manifest, Manifest)>,
+    pub removed: Vec<Manifest>,
+}
+
+impl ManifestDiff {
+    /// Test whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    /// Apply the diff to a ManifestRepo, so that a long running tool may keep
+    /// a repo up to date without rebuilding it.
+    pub fn apply(&self, repo: &mut ManifestRepo) {
+        for manifest in &self.removed {
+            repo.remove(manifest.package());
+        }
+        for manifest in self.added.iter().chain(self.modified.iter().map(|(_, new)| new)) {
+            repo.add(manifest.clone());
+        }
+    }
+}
+
+/// The ManifestCache holds the effective manifests of a directory, along with
+/// the path, size, modification time and content hash of each file read. On
+/// reload, only files which were added or whose contents changed are parsed.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut cache = ManifestCache::load("manifests")?;
+/// let arena = ManifestArena::new();
+/// let mut repo = cache.repo(&arena);
+/// // ... later
+/// let diff = cache.reload()?;
+/// diff.apply(&mut repo);
+/// ```
+#[derive(Debug)]
+pub struct ManifestCache {
+    dir: PathBuf,
+    files: HashMap<PathBuf, CachedFile>,
+    // files which failed to load, as they were when they failed
+    failed: HashMap<PathBuf, SourceFile>,
+    effective: HashMap<Package, Manifest>,
+}
+
+impl ManifestCache {
+    /// New up an empty cache for a directory. Nothing is read until `reload`
+    /// is called.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            files: HashMap::new(),
+            failed: HashMap::new(),
+            effective: HashMap::new(),
+        }
+    }
+
+    /// New up a cache for a directory and load it
+    pub fn load<P: Into<PathBuf>>(dir: P) -> Result<Self, VersionitisError> {
+        let mut cache = Self::new(dir);
+        cache.reload()?;
+        Ok(cache)
+    }
+
+    /// Retrieve the directory which the cache tracks
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Bring the cache up to date with its directory, returning the effective
+    /// manifests which were added, modified or removed. If any changed file
+    /// fails to load, an error is returned and the cache is left untouched.
+    pub fn reload(&mut self) -> Result<ManifestDiff, VersionitisError> {
+        let mut scan = self.scan(false)?;
+        if !scan.failures.is_empty() {
+            return Err(scan.failures.remove(0).2);
+        }
+        if scan.is_empty() {
+            return Ok(ManifestDiff::default());
+        }
+        let files = self.updated_files(scan);
+        let effective = resolve(&files)?;
+        let diff = diff(&self.effective, &effective);
+        self.files = files;
+        self.effective = effective;
+        Ok(diff)
+    }
+
+    /// Bring the cache up to date with its directory, tolerating bad files.
+    /// A file which fails to load keeps the manifest it last loaded as, if any,
+    /// and is not reported again until it changes. If the manifests fail to
+    /// resolve, for instance because an edit introduced an inheritance cycle,
+    /// the effective manifests are left as they were.
+    ///
+    /// Returns the diff along with the errors encountered, keyed by path.
+    pub fn refresh(&mut self) -> Result<(ManifestDiff, Vec<(PathBuf, VersionitisError)>), VersionitisError> {
+        let mut scan = self.scan(true)?;
+        let mut errors = Vec::new();
+        for (path, source, error) in scan.failures.drain(..) {
+            self.failed.insert(path.clone(), source);
+            errors.push((path, error));
+        }
+        for (path, _) in &scan.updates {
+            self.failed.remove(path);
+        }
+        for path in &scan.removed {
+            self.failed.remove(path);
+        }
+        self.failed.retain(|path, _| path.exists());
+        if scan.is_empty() {
+            return Ok((ManifestDiff::default(), errors));
+        }
+
+        self.files = self.updated_files(scan);
+        match resolve(&self.files) {
+            Ok(effective) => {
+                let diff = diff(&self.effective, &effective);
+                self.effective = effective;
+                Ok((diff, errors))
+            }
+            Err(error) => {
+                errors.push((self.dir.clone(), error));
+                Ok((ManifestDiff::default(), errors))
+            }
+        }
+    }
+
+    // read and parse the files which changed since the last load. When
+    // skip_failed is true, files which failed to load and have not changed
+    // since are skipped.
+    fn scan(&self, skip_failed: bool) -> Result<Scan, VersionitisError> {
+        if !self.dir.is_dir() {
+            return Err(VersionitisError::IoError(format!("path: {:?} does not exist", self.dir)));
+        }
+        let paths = source_paths(&self.dir)?;
+        let mut scan = Scan::default();
+        for path in &paths {
+            let cached = self.files.get(path);
+            if cached.map(|c| c.source.metadata_matches()).unwrap_or(false) {
+                continue;
+            }
+            if skip_failed && self.failed.get(path).map(|s| s.metadata_matches()).unwrap_or(false) {
+                continue;
+            }
+            let (source, contents) = match SourceFile::read(path.clone()) {
+                Ok(read) => read,
+                Err(error) => {
+                    // the file may have been removed since the directory was listed
+                    if path.exists() {
+                        return Err(error);
+                    }
+                    continue;
+                }
+            };
+            let entry = match cached {
+                // touched, but not changed
+                Some(cached) if cached.source.hash() == source.hash() => cached.entry.clone(),
+                _ => match self.parse(path, &contents) {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        scan.failures.push((path.clone(), source, error));
+                        continue;
+                    }
+                },
+            };
+            scan.updates.push((path.clone(), CachedFile { source, entry }));
+        }
+        scan.removed = self.files.keys().filter(|path| !paths.contains(path)).cloned().collect();
+        Ok(scan)
+    }
+
+    // the cached files with a scan applied
+    fn updated_files(&self, scan: Scan) -> HashMap<PathBuf, CachedFile> {
+        let mut files = self.files.clone();
+        for path in &scan.removed {
+            files.remove(path);
+        }
+        files.extend(scan.updates);
+        files
+    }
+
+    /// Retrieve the effective manifest for a package
+    pub fn get(&self, package: &Package) -> Option<&Manifest> {
+        self.effective.get(package)
+    }
+
+    /// Retrieve the effective manifests, sorted by package
+    pub fn manifests(&self) -> Vec<&Manifest> {
+        let mut manifests = self.effective.values().collect::<Vec<_>>();
+        manifests.sort_by(|a, b| a.package().cmp(b.package()));
+        manifests
+    }
+
+    /// Retrieve the number of effective manifests
+    pub fn len(&self) -> usize {
+        self.effective.len()
+    }
+
+    /// Test whether the cache holds no manifests
+    pub fn is_empty(&self) -> bool {
+        self.effective.is_empty()
+    }
+
+    /// Build a ManifestRepo holding the effective manifests
+    pub fn repo<'a, 'b>(&self, arena: &'b ManifestArena) -> ManifestRepo<'a, 'b> {
+        let mut repo = ManifestRepo::new(arena);
+        for manifest in self.effective.values() {
+            repo.add(manifest.clone());
+        }
+        repo
+    }
+
+    // parse a manifest or, if the file lives in the template directory, a template
+    fn parse(&self, path: &Path, contents: &str) -> Result<Entry, VersionitisError> {
+        let format = Format::from_path(path)?;
+        if path.parent() == Some(self.dir.join(TEMPLATE_DIR).as_path()) {
+            let name = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| VersionitisError::IoError(format!("invalid template path: {:?}", path)))?;
+            Ok(Entry::Template(name.to_string(), format.parse(contents)?))
+        } else {
+            Ok(Entry::Manifest(Box::new(format.parse_manifest(contents)?)))
+        }
+    }
+}
+
+// flatten the cached manifests into effective manifests
+fn resolve(files: &HashMap<PathBuf, CachedFile>) -> Result<HashMap<Package, Manifest>, VersionitisError> {
+    // files are visited in order of path, so that a package declared twice
+    // resolves the same way from one load to the next
+    let mut paths = files.keys().collect::<Vec<_>>();
+    paths.sort();
+    let mut resolver = ManifestResolver::new();
+    for path in paths {
+        match &files[path].entry {
+            Entry::Manifest(manifest) => resolver.add_manifest(manifest.as_ref().clone()),
+            Entry::Template(name, document) => resolver.add_template(name.as_str(), document.clone())?,
+        }
+    }
+    Ok(resolver
+        .resolve_all()?
+        .into_iter()
+        .map(|manifest| (*manifest.package(), manifest))
+        .collect())
+}
+
+// compute the difference between two sets of effective manifests
+fn diff(old: &HashMap<Package, Manifest>, new: &HashMap<Package, Manifest>) -> ManifestDiff {
+    let mut diff = ManifestDiff::default();
+    for (package, manifest) in new {
+        match old.get(package) {
+            None => diff.added.push(manifest.clone()),
+            Some(previous) if previous != manifest => diff.modified.push((previous.clone(), manifest.clone())),
+            _ => (),
+        }
+    }
+    for (package, manifest) in old {
+        if !new.contains_key(package) {
+            diff.removed.push(manifest.clone());
+        }
+    }
+    diff.added.sort_by(|a, b| a.package().cmp(b.package()));
+    diff.modified.sort_by(|a, b| a.1.package().cmp(b.1.package()));
+    diff.removed.sort_by(|a, b| a.package().cmp(b.package()));
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ScratchDir;
+    use std::fs;
+
+    // copy the manifest_inheritance test resources into a scratch directory
+    fn scratch(name: &str) -> ScratchDir {
+        let mut source = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        source.push("test_resources");
+        source.push("manifest_inheritance");
+        let dir = ScratchDir::new(name);
+        dir.copy_from(source, "");
+        dir
+    }
+
+    fn specs(manifests: &[Manifest]) -> Vec<String> {
+        manifests.iter().map(|m| m.package().spec()).collect()
+    }
+
+    #[test]
+    fn first_load_adds_every_manifest() {
+        let dir = scratch("cache-first");
+        let mut cache = ManifestCache::new(dir.path());
+        let diff = cache.reload().unwrap();
+        assert_eq!(specs(&diff.added), vec!["foo-1.0.0", "foo-1.1.0"]);
+        assert!(diff.modified.is_empty() && diff.removed.is_empty());
+        assert!(cache.reload().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reload_reports_added_modified_and_removed_manifests() {
+        let dir = scratch("cache-reload");
+        let mut cache = ManifestCache::load(dir.path()).unwrap();
+        let arena = ManifestArena::new();
+        let mut repo = cache.repo(&arena);
+
+        // rewriting a file with the same contents changes nothing
+        let foo = dir.join("foo-1.0.0.yaml");
+        let contents = fs::read_to_string(&foo).unwrap();
+        fs::write(&foo, &contents).unwrap();
+        assert!(cache.reload().unwrap().is_empty());
+
+        // a template edit modifies every manifest which inherits from it
+        fs::write(dir.join(TEMPLATE_DIR).join("base.yaml"), "dependencies:\n  abc: '0.2.0'\n").unwrap();
+        fs::write(dir.join("bar-0.1.0.yaml"), "name: bar-0.1.0\ndependencies: {}\n").unwrap();
+        fs::remove_file(dir.join("foo-1.1.0.json")).unwrap();
+        let diff = cache.reload().unwrap();
+        assert_eq!(specs(&diff.added), vec!["bar-0.1.0"]);
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].1.dependencies().get("abc").unwrap().to_string(), "0.2.0");
+        assert_eq!(specs(&diff.removed), vec!["foo-1.1.0"]);
+
+        diff.apply(&mut repo);
+        assert_eq!(repo.len(), 2);
+        assert!(repo.get("foo-1.1.0").is_none());
+        assert_eq!(repo.get("foo-1.0.0"), cache.get(&Package::from_str("foo-1.0.0").unwrap()));
+    }
+
+    #[test]
+    fn malformed_files_leave_the_cache_untouched() {
+        let dir = scratch("cache-malformed");
+        let mut cache = ManifestCache::load(dir.path()).unwrap();
+        fs::write(dir.join("foo-1.0.0.yaml"), "name: foo-1.0.0\ndependencies: [").unwrap();
+        assert!(cache.reload().is_err());
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&Package::from_str("foo-1.0.0").unwrap()).unwrap().depends_on("bla"));
+    }
+}
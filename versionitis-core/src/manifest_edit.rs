@@ -0,0 +1,376 @@
+//! manifest_edit.rs
+//!
+//! Edit the dependencies of a manifest file in place, preserving the
+//! comments, ordering and quoting of the original yaml.
+//!
+use crate::{
+    errors::VersionitisError,
+    manifest::{Manifest, Phase},
+    version_number_interval::VersionNumberInterval,
+};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Edit the dependency sections of manifest yaml without reserializing it.
+/// Only the lines touched by an edit change; everything else, comments
+/// included, is written back verbatim, with the file's own line endings.
+///
+/// The editor understands block mappings (one `name: interval` per line) and
+/// empty flow mappings (`dependencies: {}`). Every edit is checked by parsing
+/// the result as a Manifest, and rolled back if it does not parse.
+///
+/// # Example
+///
+/// ```
+/// use versionitis::manifest_edit::ManifestEditor;
+/// use versionitis::manifest::Phase;
+/// use versionitis::version_number_interval::VersionNumberInterval;
+/// let yaml = "name: foo-1.0.0\ndependencies:\n  # pinned until bar fixes its abi\n  bar: '0.1.0'\n";
+/// let mut editor = ManifestEditor::new(yaml).unwrap();
+/// editor.set_dependency(Phase::Run, "bar", &VersionNumberInterval::from_str("0.2.0").unwrap()).unwrap();
+/// assert_eq!(editor.to_string(), "name: foo-1.0.0\ndependencies:\n  # pinned until bar fixes its abi\n  bar: '0.2.0'\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEditor {
+    lines: Vec<String>,
+    // the line ending of the original yaml, judged by its first line
+    newline: &'static str,
+    trailing_newline: bool,
+}
+
+// The location of a `name: value` entry within a line
+struct Entry {
+    indent: usize,
+    name: String,
+    value_start: usize,
+    value_end: usize,
+    quote: Option<char>,
+}
+
+impl ManifestEditor {
+    /// New up an editor over manifest yaml, returning an error if the yaml is
+    /// not a valid manifest.
+    pub fn new(yaml: &str) -> Result<Self, VersionitisError> {
+        let editor = Self {
+            lines: yaml.lines().map(|line| line.to_string()).collect(),
+            newline: match yaml.find('\n') {
+                Some(idx) if yaml[..idx].ends_with('\r') => "\r\n",
+                _ => "\n",
+            },
+            trailing_newline: yaml.ends_with('\n'),
+        };
+        editor.manifest()?;
+        Ok(editor)
+    }
+
+    /// New up an editor over a manifest file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, VersionitisError> {
+        Self::new(fs::read_to_string(path)?.as_str())
+    }
+
+    /// Write the edited yaml to a file
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), VersionitisError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Parse the edited yaml as a Manifest
+    pub fn manifest(&self) -> Result<Manifest, VersionitisError> {
+        crate::schema::load_manifest(self.to_string().as_str())
+    }
+
+    /// Set the interval of a dependency, adding the dependency if it does not
+    /// exist. An existing entry keeps its quoting and any trailing comment. A
+    /// new entry is appended to the section, following the indentation and
+    /// quoting of its siblings, and the section is created if need be. An
+    /// unquoted value which yaml would not read as a string (eg 1.8) is quoted.
+    pub fn set_dependency(&mut self, phase: Phase, name: &str, interval: &VersionNumberInterval) -> Result<(), VersionitisError> {
+        let backup = self.lines.clone();
+        let value = interval.to_range();
+        let key = phase.section();
+
+        match self.find_section(key)? {
+            None => {
+                self.lines.push(format!("{}:", key));
+                self.lines.push(format!("  {}: '{}'", name, value));
+            }
+            Some(start) => {
+                let entries = self.entries(start);
+                match entries.iter().find(|(_, entry)| entry.name == name) {
+                    Some((idx, entry)) => {
+                        let line = &self.lines[*idx];
+                        let replaced = format!(
+                            "{}{}{}",
+                            &line[..entry.value_start],
+                            quote(&value, entry.quote),
+                            &line[entry.value_end..]
+                        );
+                        self.lines[*idx] = replaced;
+                    }
+                    None => {
+                        let (indent, quote_char) = entries
+                            .first()
+                            .map(|(_, entry)| (entry.indent, entry.quote))
+                            .unwrap_or((2, Some('\'')));
+                        let position = entries.last().map(|(idx, _)| idx + 1).unwrap_or(start + 1);
+                        self.lines.insert(
+                            position,
+                            format!("{}{}: {}", " ".repeat(indent), name, quote(&value, quote_char)),
+                        );
+                    }
+                }
+            }
+        }
+        self.validate(backup)
+    }
+
+    /// Remove a dependency, returning false if the manifest did not have it.
+    /// A section left without entries becomes an empty flow mapping.
+    pub fn remove_dependency(&mut self, phase: Phase, name: &str) -> Result<bool, VersionitisError> {
+        let backup = self.lines.clone();
+        let key = phase.section();
+        let start = match self.find_section(key)? {
+            Some(start) => start,
+            None => return Ok(false),
+        };
+        let entries = self.entries(start);
+        let idx = match entries.iter().find(|(_, entry)| entry.name == name) {
+            Some((idx, _)) => *idx,
+            None => {
+                // undo any conversion of an empty flow mapping
+                self.lines = backup;
+                return Ok(false);
+            }
+        };
+        self.lines.remove(idx);
+        if entries.len() == 1 {
+            let comment = comment(&self.lines[start][key.len() + 1..]);
+            self.lines[start] = format!("{}: {{}}{}", key, comment);
+        }
+        self.validate(backup)?;
+        Ok(true)
+    }
+
+    // locate the line introducing a section, converting an empty flow mapping
+    // into an empty block mapping so that entries may be added to it
+    fn find_section(&mut self, key: &str) -> Result<Option<usize>, VersionitisError> {
+        let start = match self.lines.iter().position(|line| {
+            line.starts_with(key) && line[key.len()..].starts_with(':')
+        }) {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+        let rest = self.lines[start][key.len() + 1..].to_string();
+        let trimmed = rest.trim_start();
+        if trimmed.starts_with('{') {
+            let close = trimmed.find('}').unwrap_or(trimmed.len());
+            if !trimmed[1..close].trim().is_empty() {
+                return Err(VersionitisError::ParseError(format!(
+                    "unable to edit '{}': only empty flow mappings are supported",
+                    key
+                )));
+            }
+            self.lines[start] = format!("{}:{}", key, comment(&trimmed[close..]));
+        }
+        Ok(Some(start))
+    }
+
+    // retrieve the entries of the section introduced at `start`, along with
+    // their line indices
+    fn entries(&self, start: usize) -> Vec<(usize, Entry)> {
+        let mut entries = Vec::new();
+        for (idx, line) in self.lines.iter().enumerate().skip(start + 1) {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if trimmed.len() == line.len() {
+                break;
+            }
+            if let Some(entry) = parse_entry(line) {
+                entries.push((idx, entry));
+            }
+        }
+        entries
+    }
+
+    // roll back to `backup` if the edited yaml is not a valid manifest
+    fn validate(&mut self, backup: Vec<String>) -> Result<(), VersionitisError> {
+        if let Err(e) = self.manifest() {
+            self.lines = backup;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ManifestEditor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines.join(self.newline))?;
+        if self.trailing_newline {
+            write!(f, "{}", self.newline)?;
+        }
+        Ok(())
+    }
+}
+
+// parse a `name: value` line, locating the value
+fn parse_entry(line: &str) -> Option<Entry> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let (name, after_name) = match rest.chars().next()? {
+        q @ '\'' | q @ '"' => {
+            let close = rest[1..].find(q)? + 1;
+            (rest[1..close].to_string(), indent + close + 1)
+        }
+        _ => {
+            let colon = rest.find(':')?;
+            (rest[..colon].trim_end().to_string(), indent + colon)
+        }
+    };
+    let after_colon = after_name + line[after_name..].find(':')? + 1;
+    let value_start = after_colon + (line[after_colon..].len() - line[after_colon..].trim_start().len());
+    let value = &line[value_start..];
+    let (quote, value_end) = match value.chars().next() {
+        Some(q @ '\'') | Some(q @ '"') => (Some(q), value_start + value[1..].find(q)? + 2),
+        _ => {
+            let end = value.find(" #").unwrap_or(value.len());
+            (None, value_start + value[..end].trim_end().len())
+        }
+    };
+    Some(Entry { indent, name, value_start, value_end, quote })
+}
+
+// wrap a value in the supplied quote character, if any. A value which yaml
+// would read as anything other than itself (eg 1.8 as a float) is quoted
+// regardless.
+fn quote(value: &str, quote: Option<char>) -> String {
+    match quote {
+        Some(q) => format!("{}{}{}", q, value, q),
+        None if is_plain_string(value) => value.to_string(),
+        None => format!("'{}'", value),
+    }
+}
+
+// test whether yaml reads an unquoted value as the same string
+fn is_plain_string(value: &str) -> bool {
+    match serde_yaml::from_str::<serde_yaml::Value>(value) {
+        Ok(serde_yaml::Value::String(s)) => s == value,
+        _ => false,
+    }
+}
+
+// extract a trailing comment, including its leading whitespace
+fn comment(rest: &str) -> String {
+    match rest.find('#') {
+        Some(idx) => format!(" {}", &rest[idx..]),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "---
+# foo is the foundation of everything
+name: foo-1.0.0
+dependencies:
+  # bla 0.3 broke the abi, see ticket 42
+  bla: '0.2.0<=1.0.0'   # keep this pin
+  abc: \"0.1.0\"
+  bar: 0.1.0<2.0.0
+build_dependencies: {}  # none yet
+";
+
+    fn vi(range: &str) -> VersionNumberInterval {
+        VersionNumberInterval::from_str(range).unwrap()
+    }
+
+    #[test]
+    fn can_change_an_interval_preserving_layout() {
+        let mut editor = ManifestEditor::new(YAML).unwrap();
+        editor.set_dependency(Phase::Run, "bla", &vi("0.2.0<2.0.0")).unwrap();
+        editor.set_dependency(Phase::Run, "abc", &vi("0.2.0")).unwrap();
+        editor.set_dependency(Phase::Run, "bar", &vi("0.1.0<=3.0.0")).unwrap();
+        assert_eq!(
+            editor.to_string(),
+            YAML.replace("'0.2.0<=1.0.0'", "'0.2.0<2.0.0'")
+                .replace("\"0.1.0\"", "\"0.2.0\"")
+                .replace("0.1.0<2.0.0", "0.1.0<=3.0.0")
+        );
+    }
+
+    #[test]
+    fn can_add_dependencies() {
+        let mut editor = ManifestEditor::new(YAML).unwrap();
+        editor.set_dependency(Phase::Run, "zed", &vi("1.0.0")).unwrap();
+        editor.set_dependency(Phase::Build, "cmake", &vi("3.14<4.0")).unwrap();
+        editor.set_dependency(Phase::Test, "gtest", &vi("1.8")).unwrap();
+        assert_eq!(
+            editor.to_string(),
+            YAML.replace("0.1.0<2.0.0\n", "0.1.0<2.0.0\n  zed: '1.0.0'\n")
+                .replace("build_dependencies: {}  # none yet\n", "build_dependencies: # none yet\n  cmake: '3.14<4.0'\n")
+                + "test_dependencies:\n  gtest: '1.8'\n"
+        );
+        let manifest = editor.manifest().unwrap();
+        assert!(manifest.dependencies_for(Phase::Build).contains_key("cmake"));
+        assert!(manifest.dependencies_for(Phase::Test).contains_key("gtest"));
+    }
+
+    #[test]
+    fn can_remove_dependencies() {
+        let mut editor = ManifestEditor::new(YAML).unwrap();
+        assert!(editor.remove_dependency(Phase::Run, "abc").unwrap());
+        assert!(!editor.remove_dependency(Phase::Run, "nope").unwrap());
+        assert!(!editor.remove_dependency(Phase::Dev, "abc").unwrap());
+        assert_eq!(editor.to_string(), YAML.replace("  abc: \"0.1.0\"\n", ""));
+
+        editor.set_dependency(Phase::Build, "cmake", &vi("3.14")).unwrap();
+        assert!(editor.remove_dependency(Phase::Build, "cmake").unwrap());
+        assert!(editor.to_string().ends_with("build_dependencies: {} # none yet\n"));
+    }
+
+    #[test]
+    fn values_yaml_would_not_read_as_strings_are_quoted() {
+        let yaml = "name: foo-1.0.0\ndependencies:\n  bar: 0.1.0<1.0.0\n";
+        let mut editor = ManifestEditor::new(yaml).unwrap();
+        // a new entry follows its unquoted sibling, unless it would read as a float
+        editor.set_dependency(Phase::Run, "baz", &vi("1.8")).unwrap();
+        editor.set_dependency(Phase::Run, "bla", &vi("1.8<2.0")).unwrap();
+        // an existing unquoted entry is quoted if it would read as an integer
+        editor.set_dependency(Phase::Run, "bar", &vi("2")).unwrap();
+        assert_eq!(
+            editor.to_string(),
+            "name: foo-1.0.0\ndependencies:\n  bar: '2'\n  baz: '1.8'\n  bla: 1.8<2.0\n"
+        );
+        let manifest = editor.manifest().unwrap();
+        assert_eq!(manifest.dependencies_for(Phase::Run).get("baz"), Some(&vi("1.8")));
+        assert_eq!(manifest.dependencies_for(Phase::Run).get("bar"), Some(&vi("2")));
+    }
+
+    #[test]
+    fn line_endings_are_preserved() {
+        let yaml = "name: foo-1.0.0\r\ndependencies:\r\n  # pinned\r\n  bar: '0.1.0'\r\n";
+        let mut editor = ManifestEditor::new(yaml).unwrap();
+        assert_eq!(editor.to_string(), yaml);
+        editor.set_dependency(Phase::Run, "bar", &vi("0.2.0")).unwrap();
+        editor.set_dependency(Phase::Run, "baz", &vi("1.0.0")).unwrap();
+        assert_eq!(
+            editor.to_string(),
+            "name: foo-1.0.0\r\ndependencies:\r\n  # pinned\r\n  bar: '0.2.0'\r\n  baz: '1.0.0'\r\n"
+        );
+
+        // without a trailing line ending, none is added
+        let mut editor = ManifestEditor::new("name: foo-1.0.0\r\ndependencies: {}").unwrap();
+        editor.set_dependency(Phase::Run, "bar", &vi("0.2.0")).unwrap();
+        assert_eq!(editor.to_string(), "name: foo-1.0.0\r\ndependencies:\r\n  bar: '0.2.0'");
+    }
+
+    #[test]
+    fn non_empty_flow_mappings_are_not_supported() {
+        let mut editor = ManifestEditor::new("name: foo-1.0.0\ndependencies: { bar: '0.1.0' }\n").unwrap();
+        assert!(editor.set_dependency(Phase::Run, "bar", &vi("0.2.0")).is_err());
+    }
+}
@@ -2,21 +2,26 @@
 //!
 //! create a struct which owns package manifests.
 //!
-use crate::manifest::Manifest;
+use crate::manifest::{Manifest, Phase};
+use crate::inheritance::ManifestResolver;
 use crate::errors::VersionitisError;
-use std::collections::{HashMap, hash_map::Keys};
+use crate::package::Package;
+use crate::version_number_interval::VersionNumberInterval;
+use std::collections::{HashMap, VecDeque, hash_map::Keys};
 use typed_arena::Arena;
 use std::collections::HashSet;
 use std::path::PathBuf;
-use std::fs;
+use std::str::FromStr;
 
-pub type PackageName = str;
 pub type ManifestArena = Arena<Manifest>;
-pub type _ManifestMap<'a> = HashMap<&'a PackageName, &'a Manifest>;
+pub type _ManifestMap<'a> = HashMap<&'a Package, &'a Manifest>;
+pub type _ProviderMap<'a> = HashMap<&'a str, Vec<(&'a Package, &'a Manifest)>>;
 
 pub struct ManifestRepo<'a, 'b: 'a> {
     arena: &'b ManifestArena,
     map: _ManifestMap<'a>,
+    // index virtual package names to the manifests which provide them
+    providers: _ProviderMap<'a>,
 }
 
 impl<'a, 'b> std::fmt::Debug for ManifestRepo<'a, 'b> {
@@ -31,10 +36,13 @@ impl<'a, 'b> ManifestRepo<'a, 'b> {
         Self {
             arena,
             map: _ManifestMap::new(),
+            providers: _ProviderMap::new(),
         }
     }
 
-    /// construct a ManifestRepo from a directory full of manifests
+    /// construct a ManifestRepo from a directory full of manifests, resolving
+    /// any manifest which extends another manifest or a template. Files are
+    /// parsed in parallel, while manifests are added to the arena in order.
     pub fn from_disk<P: Into<PathBuf>>(path: P, arena: &'b ManifestArena) -> Result<Self, VersionitisError> {
         // get path to directory
         let path = path.into();
@@ -43,53 +51,76 @@ impl<'a, 'b> ManifestRepo<'a, 'b> {
         }
 
         let mut repo = ManifestRepo::new(arena);
-
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let file = fs::read_to_string(path)?;
-                let manifest = serde_yaml::from_str(file.as_str())?;
-                repo.add(manifest);
-            }
+        // manifests are stored flattened, so that each one holds its
+        // effective declarations
+        for manifest in ManifestResolver::from_disk(path)?.resolve_all()? {
+            repo.add(manifest);
         }
 
         Ok(repo)
     }
 
-    /// Add a manifest into the manifest_repo
+    /// Add a manifest into the manifest_repo. If a manifest for the same
+    /// package exists, it is replaced.
     pub fn add(&mut self, manifest: Manifest) {
         let manifest: &'b Manifest = self.arena.alloc(manifest);
         let key = manifest.package();
-        self.map.insert(key, manifest);
+        if let Some(old) = self.map.insert(key, manifest) {
+            for provided in old.provides() {
+                if let Some(providers) = self.providers.get_mut(provided.name()) {
+                    providers.retain(|(_, m)| !std::ptr::eq(*m, old));
+                }
+            }
+        }
+        for provided in manifest.provides() {
+            self.providers.entry(provided.name()).or_default().push((provided, manifest));
+        }
     }
 
-    /// Retrieve an option wrapped Manifest reference given a package name.
-    pub fn get(&self, name: &str) -> Option<&'a Manifest> {
-        match self.map.get(name) {
-            Some(map) => Some(*map),
-            None => None
+    /// Remove the manifest for a package, returning it if it was present. The
+    /// manifest remains allocated in the arena until the arena is dropped.
+    pub fn remove(&mut self, package: &Package) -> Option<&'a Manifest> {
+        let old = self.map.remove(package)?;
+        for provided in old.provides() {
+            if let Some(providers) = self.providers.get_mut(provided.name()) {
+                providers.retain(|(_, m)| !std::ptr::eq(*m, old));
+                if providers.is_empty() {
+                    self.providers.remove(provided.name());
+                }
+            }
         }
+        Some(old)
+    }
+
+    /// Retrieve an option wrapped Manifest reference given a versioned package
+    /// name (eg foo-0.1.0). If the name does not parse, None is returned.
+    pub fn get(&self, name: &str) -> Option<&'a Manifest> {
+        let package = Package::from_str(name).ok()?;
+        self.get_package(&package)
+    }
+
+    /// Retrieve an option wrapped Manifest reference given a Package.
+    pub fn get_package(&self, package: &Package) -> Option<&'a Manifest> {
+        self.map.get(package).cloned()
     }
 
     /// Retrieve an iterator over keys
-    pub fn keys(&self) -> Keys<&'a PackageName, &'a Manifest> {
+    pub fn keys(&self) -> Keys<&'a Package, &'a Manifest> {
         self.map.keys()
     }
 
     /// retrieve a hashset of packages. If versioned is true, we get
     /// all versioned packages. Otherwise, we simply get the set of
     /// package basenames (eg foo as opposed to foo-0.1.0)
-    pub fn packages(&self, versioned: bool) -> HashSet<&'a str> {
-        let mut hashset: HashSet<&'a str> = HashSet::new();
+    pub fn packages(&self, versioned: bool) -> HashSet<String> {
+        let mut hashset: HashSet<String> = HashSet::new();
         if versioned == true {
             for key in self.keys() {
-                hashset.insert(key);
+                hashset.insert(key.spec());
             }
         } else {
             for key in self.keys() {
-                let retstr = key.split("-").next().unwrap();
-                hashset.insert(retstr);
+                hashset.insert(key.name().to_string());
             }
         }
         hashset
@@ -97,30 +128,240 @@ impl<'a, 'b> ManifestRepo<'a, 'b> {
 
      /// retrieve a hashset of packages. If versioned is true, we get all of the
      /// package versions. Otherwise, we get the unique set of package names, sorted.
-    pub fn packages_sorted(&self, versioned: bool) -> Vec<&'a str> {
-        let hashset: HashSet<&'a str> = self.packages(versioned);
-        let mut packages = hashset.iter().map(|x| *x).collect::<Vec<& str>>();
+    pub fn packages_sorted(&self, versioned: bool) -> Vec<String> {
+        let hashset: HashSet<String> = self.packages(versioned);
+        let mut packages = hashset.into_iter().collect::<Vec<String>>();
         packages.sort();
         packages
     }
-    /// Given a package name, determine whether the manifest map
+    /// Given a versioned package name, determine whether the manifest map
     /// contains the manifest version or not.
     pub fn has(&self, package: &str) -> bool {
-        self.map.contains_key(package)
+        self.get(package).is_some()
     }
 
     /// Given a &str representing a valid manifest name, create a Manifest
-    /// and add it into the ManifestRepo
-    pub fn add_str(&mut self, vs: &str) {
-        // todo: deal with error
-        let version_num = Manifest::new(vs);
+    /// and add it into the ManifestRepo. An error is returned if the name
+    /// does not parse.
+    pub fn add_str(&mut self, vs: &str) -> Result<(), VersionitisError> {
+        let version_num = Manifest::from_str(vs)?;
         self.add(version_num);
+        Ok(())
     }
 
     /// Retrieve the number of elements stored
     pub fn len(&self) -> usize {
         self.map.len()
     }
+
+    /// Retrieve the manifests for every version of the named package,
+    /// sorted from lowest to highest version.
+    pub fn versions(&self, name: &str) -> Vec<&'a Manifest> {
+        let mut versions = self.map.iter()
+            .filter(|(package, _)| package.name() == name)
+            .map(|(_, manifest)| *manifest)
+            .collect::<Vec<&'a Manifest>>();
+        versions.sort_by(|a, b| a.package().cmp(b.package()));
+        versions
+    }
+
+    /// Retrieve the manifests for every variant of a package's name and version,
+    /// including the plain package, sorted by variant.
+    pub fn variants(&self, package: &Package) -> Vec<&'a Manifest> {
+        let mut variants = self.map.iter()
+            .filter(|(key, _)| key.same_version(package))
+            .map(|(_, manifest)| *manifest)
+            .collect::<Vec<&'a Manifest>>();
+        variants.sort_by(|a, b| a.package().cmp(b.package()));
+        variants
+    }
+
+    /// Retrieve the manifest for the highest version of the named package
+    /// which the interval contains. Variants are not distinguished: when the
+    /// highest version has several, the greatest is returned. Use `closure` to
+    /// select the variant suited to a particular package.
+    pub fn latest_matching(&self, name: &str, interval: &VersionNumberInterval) -> Option<&'a Manifest> {
+        self.versions(name)
+            .into_iter()
+            .rev()
+            .find(|manifest| interval.contains(manifest.version_number()))
+    }
+
+    /// Retrieve the manifests which provide a version of the named virtual
+    /// package, paired with the virtual package they provide. Results are sorted
+    /// from lowest to highest provided version.
+    pub fn providers(&self, name: &str) -> Vec<(&'a Package, &'a Manifest)> {
+        let mut providers = self.providers.get(name).cloned().unwrap_or_default();
+        providers.sort_by(|a, b| (a.0, a.1.package()).cmp(&(b.0, b.1.package())));
+        providers
+    }
+
+    /// Retrieve the manifests able to satisfy a dependency on the named package,
+    /// paired with the (real or virtual) package which satisfies it. Real versions
+    /// of the package are preferred over providers, and higher versions over lower.
+    /// Every variant of each version is included.
+    pub fn satisfying(&self, name: &str, interval: &VersionNumberInterval) -> Vec<(&'a Package, &'a Manifest)> {
+        let real = self.versions(name)
+            .into_iter()
+            .rev()
+            .map(|manifest| (manifest.package(), manifest));
+        let virtual_ = self.providers(name).into_iter().rev();
+        real.chain(virtual_)
+            .filter(|(package, _)| interval.contains(package.version_number()))
+            .collect()
+    }
+
+    /// Compute the transitive closure of a package's dependencies, following only
+    /// the dependencies for the supplied phase. Each dependency is satisfied by
+    /// the highest version in the repo which its interval contains, and which
+    /// does not conflict with any package selected so far. A dependency on a
+    /// virtual package is satisfied by a provider when no real package matches.
+    /// Of the variants of a version, the one sharing the most qualifiers with the
+    /// package's variant is chosen, falling back to the plain package; variants
+    /// with a qualifier the package lacks are never chosen. A plain package may
+    /// use a variant when it is the only build of its version. If two variants
+    /// are equally suited, the dependency is ambiguous, and an error is returned.
+    /// Once a version of a package (real or virtual) has been selected, every
+    /// other requirement on that package must contain it. The closure does not
+    /// include the package itself, and is sorted by package.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let run_closure = repo.closure(&Package::from_str("foo-1.0.0")?, Phase::Run)?;
+    /// ```
+    pub fn closure(&self, package: &Package, phase: Phase) -> Result<Vec<&'a Manifest>, VersionitisError> {
+        let root = self.get_package(package)
+            .ok_or_else(|| VersionitisError::UnknownPackage(package.spec()))?;
+        // map each selected package name, real or virtual, to the package
+        // satisfying it and the manifest which supplies that package
+        let mut selected: HashMap<&'a str, (&'a Package, &'a Manifest)> = HashMap::new();
+        let mut chosen_manifests: Vec<&'a Manifest> = Vec::new();
+        let mut queue = VecDeque::new();
+        Self::select(root, &mut selected, &mut chosen_manifests);
+        queue.push_back(root);
+
+        while let Some(manifest) = queue.pop_front() {
+            let dependencies = manifest.dependencies_for(phase);
+            // visit dependencies in name order so that results are deterministic
+            let mut names = dependencies.keys().collect::<Vec<&String>>();
+            names.sort();
+            for name in names {
+                let interval = dependencies.get(name).unwrap();
+                if let Some((package, _)) = selected.get(name.as_str()) {
+                    if !interval.contains(package.version_number()) {
+                        return Err(VersionitisError::ConflictingDependency(format!(
+                            "{} requires {}: '{}', but {} has been selected",
+                            manifest.package(), name, interval, package
+                        )));
+                    }
+                    continue;
+                }
+                // a provider may only be used if it, or another version of it,
+                // has not already been selected
+                let selectable = |candidate: &Manifest| {
+                    let unselected = match selected.get(candidate.name()) {
+                        Some((package, _)) => *package == candidate.package(),
+                        None => true,
+                    };
+                    unselected && chosen_manifests.iter().all(|s| s.is_compatible_with(candidate))
+                };
+                // consider the best suited variant of each version in turn
+                let mut candidates = Vec::new();
+                let mut chosen = None;
+                let versions = self.satisfying(name, interval);
+                for variants in versions.chunk_by(|a, b| a.0.same_version(b.0) && a.1.package().same_version(b.1.package())) {
+                    if let Some(candidate) = Self::best_variant(root.package(), manifest.package(), variants)? {
+                        candidates.push(candidate);
+                        if selectable(candidate.1) {
+                            chosen = Some(candidate);
+                            break;
+                        }
+                    }
+                }
+                let chosen = match (chosen, candidates.first()) {
+                    (Some(chosen), _) => chosen,
+                    (None, Some((_, candidate))) => {
+                        // report the conflict in terms of the best candidate
+                        let other = chosen_manifests.iter()
+                            .find(|s| !s.is_compatible_with(candidate))
+                            .map(|s| s.package().spec())
+                            .unwrap_or_else(|| "the selection".to_string());
+                        return Err(VersionitisError::IncompatiblePackages(format!(
+                            "{} requires {}: '{}', but every candidate conflicts with the selection (eg {} conflicts with {})",
+                            manifest.package(), name, interval, candidate.package(), other
+                        )));
+                    }
+                    (None, None) => {
+                        return Err(VersionitisError::UnresolvedDependency(format!(
+                            "{} requires {}: '{}'", manifest.package(), name, interval
+                        )));
+                    }
+                };
+                selected.insert(name.as_str(), chosen);
+                if Self::select(chosen.1, &mut selected, &mut chosen_manifests) {
+                    queue.push_back(chosen.1);
+                }
+            }
+        }
+
+        let mut closure = chosen_manifests.into_iter()
+            .filter(|manifest| manifest.package() != root.package())
+            .collect::<Vec<&'a Manifest>>();
+        closure.sort_by(|a, b| a.package().cmp(b.package()));
+        Ok(closure)
+    }
+
+    // Choose the variant of a single version best suited to the root package.
+    // For a root with a variant, that is the variant sharing the most qualifiers
+    // with it, then the plain package; a variant with a qualifier the root lacks
+    // is unsuitable. A plain root prefers the plain package, then a lone variant.
+    fn best_variant(
+        root: &Package,
+        dependent: &Package,
+        variants: &[(&'a Package, &'a Manifest)],
+    ) -> Result<Option<(&'a Package, &'a Manifest)>, VersionitisError> {
+        let fit = |candidate: &Package| {
+            let qualifiers = candidate.variant();
+            if !root.has_variant() {
+                Some(if qualifiers.is_empty() { 1 } else { 0 })
+            } else if qualifiers.iter().all(|q| root.variant().contains(q)) {
+                Some(qualifiers.len())
+            } else {
+                None
+            }
+        };
+        let mut suited = variants.iter().rev()
+            .filter_map(|candidate| fit(candidate.1.package()).map(|fit| (fit, *candidate)))
+            .collect::<Vec<_>>();
+        suited.sort_by_key(|(fit, _)| std::cmp::Reverse(*fit));
+        match suited.as_slice() {
+            [] => Ok(None),
+            [(best, first), (next, second), ..] if best == next => Err(VersionitisError::AmbiguousVariant(format!(
+                "{} requires {}, but {} and {} are equally suited to {}",
+                dependent, first.0.name(), first.1.package(), second.1.package(), root
+            ))),
+            [(_, best), ..] => Ok(Some(*best)),
+        }
+    }
+
+    // Record a manifest as part of a closure, along with the virtual packages
+    // it provides. Returns false if the manifest had already been selected.
+    fn select(
+        manifest: &'a Manifest,
+        selected: &mut HashMap<&'a str, (&'a Package, &'a Manifest)>,
+        chosen_manifests: &mut Vec<&'a Manifest>,
+    ) -> bool {
+        if chosen_manifests.iter().any(|m| m.package() == manifest.package()) {
+            return false;
+        }
+        chosen_manifests.push(manifest);
+        selected.insert(manifest.name(), (manifest.package(), manifest));
+        for provided in manifest.provides() {
+            selected.entry(provided.name()).or_insert((provided, manifest));
+        }
+        true
+    }
 }
 
 #[cfg(test)]
@@ -131,30 +372,46 @@ mod test {
     fn can_add_manifests_into_map() {
         let arena = ManifestArena::new();
         let mut repo = ManifestRepo::new(&arena);
-        repo.add(Manifest::new("foo-0.1.0"));
-        repo.add_str("foo-0.2.0");
-        repo.add_str("foo-0.2.1");
+        repo.add(Manifest::from_str("foo-0.1.0").unwrap());
+        repo.add_str("foo-0.2.0").unwrap();
+        repo.add_str("foo-0.2.1").unwrap();
 
         assert_eq!(repo.len(), 3);
-        assert_eq!(repo.get("foo-0.1.0"), Some(&Manifest::new("foo-0.1.0")));
-        assert_eq!(repo.get("foo-0.2.0"), Some(&Manifest::new("foo-0.2.0")));
-        assert_eq!(repo.get("foo-0.2.1"), Some(&Manifest::new("foo-0.2.1")));
+        assert_eq!(repo.get("foo-0.1.0"), Some(&Manifest::from_str("foo-0.1.0").unwrap()));
+        assert_eq!(repo.get("foo-0.2.0"), Some(&Manifest::from_str("foo-0.2.0").unwrap()));
+        assert_eq!(repo.get("foo-0.2.1"), Some(&Manifest::from_str("foo-0.2.1").unwrap()));
         assert_eq!(repo.get("foo-bar"), None);
     }
 
 
+    #[test]
+    fn can_remove_manifests() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        let mut manifest = Manifest::from_str("mesa-19.0.0").unwrap();
+        manifest.add_provides(Package::from_str("opengl-4.5.0").unwrap()).unwrap();
+        repo.add(manifest);
+        repo.add_str("foo-0.1.0").unwrap();
+
+        let package = Package::from_str("mesa-19.0.0").unwrap();
+        assert_eq!(repo.remove(&package).map(|m| m.package()), Some(&package));
+        assert_eq!(repo.remove(&package), None);
+        assert_eq!(repo.len(), 1);
+        assert!(repo.providers("opengl").is_empty());
+    }
+
     #[test]
     fn can_get_package_hashset_without_versions() {
         let arena = ManifestArena::new();
         let mut repo = ManifestRepo::new(&arena);
-        repo.add(Manifest::new("foo-0.1.0"));
-        repo.add_str("foo-0.2.0");
-        repo.add_str("foo-0.2.1");
-        repo.add_str("bar-0.2.0");
-        repo.add_str("bar-0.2.1");
+        repo.add(Manifest::from_str("foo-0.1.0").unwrap());
+        repo.add_str("foo-0.2.0").unwrap();
+        repo.add_str("foo-0.2.1").unwrap();
+        repo.add_str("bar-0.2.0").unwrap();
+        repo.add_str("bar-0.2.1").unwrap();
 
         let packages = repo.packages(false);
-        let mut vpackages = packages.iter().map(|x| *x).collect::<Vec<& str>>();
+        let mut vpackages = packages.iter().map(|x| x.as_str()).collect::<Vec<& str>>();
         vpackages.sort();
         let val = vec!["bar", "foo"];
         assert_eq!(vpackages, val);
@@ -165,11 +422,11 @@ mod test {
     fn can_get_ordered_packages_without_versions() {
         let arena = ManifestArena::new();
         let mut repo = ManifestRepo::new(&arena);
-        repo.add(Manifest::new("foo-0.1.0"));
-        repo.add_str("foo-0.2.0");
-        repo.add_str("foo-0.2.1");
-        repo.add_str("bar-0.2.0");
-        repo.add_str("bar-0.2.1");
+        repo.add(Manifest::from_str("foo-0.1.0").unwrap());
+        repo.add_str("foo-0.2.0").unwrap();
+        repo.add_str("foo-0.2.1").unwrap();
+        repo.add_str("bar-0.2.0").unwrap();
+        repo.add_str("bar-0.2.1").unwrap();
 
         let packages = repo.packages_sorted(false);
 
@@ -182,16 +439,225 @@ mod test {
     fn can_add_multiple_times() {
         let arena = ManifestArena::new();
         let mut repo = ManifestRepo::new(&arena);
-        repo.add(Manifest::new("foo-0.1.0"));
-        repo.add_str("foo-0.2.0");
-        repo.add_str("foo-0.2.1");
+        repo.add(Manifest::from_str("foo-0.1.0").unwrap());
+        repo.add_str("foo-0.2.0").unwrap();
+        repo.add_str("foo-0.2.1").unwrap();
         let idx = repo.get("foo-0.2.1");
-        repo.add_str("foo-0.2.1");
+        repo.add_str("foo-0.2.1").unwrap();
         let idx_after = repo.get("foo-0.2.1");
         assert_eq!(repo.len(), 3);
         assert_eq!(idx, idx_after);
     }
 
+    fn manifest(name: &str, phase: Phase, dependencies: &[(&str, &str)]) -> Manifest {
+        let mut manifest = Manifest::from_str(name).unwrap();
+        for (dependency, interval) in dependencies {
+            let interval = VersionNumberInterval::from_str(interval).unwrap();
+            manifest.add_dependency_for(phase, *dependency, interval).unwrap();
+        }
+        manifest
+    }
+
+    #[test]
+    fn can_get_versions_and_latest_matching() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add_str("foo-0.2.0").unwrap();
+        repo.add_str("foo-0.1.0").unwrap();
+        repo.add_str("foo-1.0.0").unwrap();
+        repo.add_str("bar-0.1.0").unwrap();
+
+        let versions = repo.versions("foo").iter().map(|m| m.package().spec()).collect::<Vec<String>>();
+        assert_eq!(versions, vec!["foo-0.1.0", "foo-0.2.0", "foo-1.0.0"]);
+
+        let interval = VersionNumberInterval::from_str("0.1.0<1.0.0").unwrap();
+        let latest = repo.latest_matching("foo", &interval).unwrap();
+        assert_eq!(latest.package().spec(), "foo-0.2.0");
+        assert!(repo.latest_matching("bla", &interval).is_none());
+    }
+
+    #[test]
+    fn closure_follows_a_single_phase() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add({
+            let mut app = manifest("app-1.0.0", Phase::Run, &[("lib", "1.0.0<2.0.0")]);
+            app.add_dependency_for(Phase::Build, "cmake", VersionNumberInterval::from_str("3.0.0<4.0.0").unwrap()).unwrap();
+            app
+        });
+        repo.add(manifest("lib-1.0.0", Phase::Run, &[("zlib", "1.2.0<2.0.0")]));
+        repo.add(manifest("lib-1.1.0", Phase::Run, &[("zlib", "1.2.0<2.0.0")]));
+        repo.add(manifest("lib-2.0.0", Phase::Run, &[]));
+        repo.add(manifest("zlib-1.2.11", Phase::Run, &[]));
+        repo.add(manifest("cmake-3.1.0", Phase::Run, &[]));
+
+        let app = Package::from_str("app-1.0.0").unwrap();
+        let run = repo.closure(&app, Phase::Run).unwrap()
+            .iter().map(|m| m.package().spec()).collect::<Vec<String>>();
+        assert_eq!(run, vec!["lib-1.1.0", "zlib-1.2.11"]);
+
+        let build = repo.closure(&app, Phase::Build).unwrap()
+            .iter().map(|m| m.package().spec()).collect::<Vec<String>>();
+        assert_eq!(build, vec!["cmake-3.1.0"]);
+    }
+
+    #[test]
+    fn closure_reports_unresolved_and_conflicting_dependencies() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(manifest("app-1.0.0", Phase::Run, &[("lib", "1.0.0<2.0.0"), ("zlib", "1.0.0")]));
+        repo.add(manifest("lib-1.0.0", Phase::Run, &[("zlib", "1.2.0<2.0.0")]));
+        repo.add(manifest("zlib-1.0.0", Phase::Run, &[]));
+        repo.add(manifest("zlib-1.2.11", Phase::Run, &[]));
+        repo.add(manifest("broken-1.0.0", Phase::Run, &[("missing", "1.0.0")]));
+
+        let result = repo.closure(&Package::from_str("app-1.0.0").unwrap(), Phase::Run);
+        assert_eq!(result, Err(VersionitisError::ConflictingDependency(
+            "lib-1.0.0 requires zlib: '1.2.0<2.0.0', but zlib-1.0.0 has been selected".to_string())));
+
+        let result = repo.closure(&Package::from_str("broken-1.0.0").unwrap(), Phase::Run);
+        assert_eq!(result, Err(VersionitisError::UnresolvedDependency(
+            "broken-1.0.0 requires missing: '1.0.0'".to_string())));
+
+        let result = repo.closure(&Package::from_str("nope-1.0.0").unwrap(), Phase::Run);
+        assert_eq!(result, Err(VersionitisError::UnknownPackage("nope-1.0.0".to_string())));
+    }
+
+    #[test]
+    fn closure_honors_conflicts() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(manifest("app-1.0.0", Phase::Run, &[("maya", "2019.0<2020.0"), ("plugin", "1.0.0<2.0.0")]));
+        repo.add(manifest("maya-2019.1", Phase::Run, &[]));
+        repo.add(manifest("maya-2019.2", Phase::Run, &[]));
+        repo.add(manifest("plugin-1.0.0", Phase::Run, &[]));
+        repo.add({
+            // the newest plugin crashes with the newest maya
+            let mut plugin = manifest("plugin-1.1.0", Phase::Run, &[]);
+            plugin.add_conflict("maya", VersionNumberInterval::from_str("2019.2").unwrap()).unwrap();
+            plugin
+        });
+
+        let closure = repo.closure(&Package::from_str("app-1.0.0").unwrap(), Phase::Run).unwrap()
+            .iter().map(|m| m.package().spec()).collect::<Vec<String>>();
+        assert_eq!(closure, vec!["maya-2019.2", "plugin-1.0.0"]);
+    }
+
+    #[test]
+    fn closure_reports_incompatible_packages() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(manifest("app-1.0.0", Phase::Run, &[("maya", "2019.2"), ("plugin", "1.0.0<2.0.0")]));
+        repo.add(manifest("maya-2019.2", Phase::Run, &[]));
+        repo.add({
+            let mut plugin = manifest("plugin-1.1.0", Phase::Run, &[]);
+            plugin.add_conflict("maya", VersionNumberInterval::from_str("2019.2").unwrap()).unwrap();
+            plugin
+        });
+
+        let result = repo.closure(&Package::from_str("app-1.0.0").unwrap(), Phase::Run);
+        match result {
+            Err(VersionitisError::IncompatiblePackages(_)) => {}
+            _ => panic!("expected IncompatiblePackages, got {:?}", result),
+        }
+    }
+
+    fn provider(name: &str, provides: &str) -> Manifest {
+        let mut manifest = Manifest::from_str(name).unwrap();
+        manifest.add_provides(Package::from_str(provides).unwrap()).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn manifests_may_differ_per_variant() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(manifest("foo-1.2.0[linux,gcc9]", Phase::Run, &[("glibc", "2.17<3.0")]));
+        repo.add(manifest("foo-1.2.0[windows]", Phase::Run, &[("msvcrt", "14.0<15.0")]));
+        repo.add_str("foo-1.3.0").unwrap();
+
+        assert!(repo.get("foo-1.2.0[gcc9,linux]").unwrap().depends_on("glibc"));
+        assert!(repo.get("foo-1.2.0[windows]").unwrap().depends_on("msvcrt"));
+        assert!(repo.get("foo-1.2.0").is_none());
+
+        let variants = repo.variants(&Package::from_str("foo-1.2.0").unwrap()).iter()
+            .map(|m| m.package().spec())
+            .collect::<Vec<String>>();
+        assert_eq!(variants, vec!["foo-1.2.0[gcc9,linux]", "foo-1.2.0[windows]"]);
+    }
+
+    #[test]
+    fn closure_selects_the_variant_suited_to_the_root() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(manifest("app-1.0.0[linux]", Phase::Run, &[("foo", "1.2.0"), ("bar", "1.0.0")]));
+        repo.add(manifest("app-1.0.0[gcc9,linux]", Phase::Run, &[("foo", "1.2.0")]));
+        repo.add(manifest("app-1.0.0", Phase::Run, &[("foo", "1.2.0")]));
+        repo.add(manifest("foo-1.2.0[linux]", Phase::Run, &[]));
+        repo.add(manifest("foo-1.2.0[windows]", Phase::Run, &[]));
+        repo.add(manifest("bar-1.0.0", Phase::Run, &[]));
+        repo.add(manifest("bar-1.0.0[windows]", Phase::Run, &[]));
+
+        let closure = |spec: &str| repo.closure(&Package::from_str(spec).unwrap(), Phase::Run)
+            .map(|closure| closure.iter().map(|m| m.package().spec()).collect::<Vec<String>>());
+        // a matching variant is preferred, falling back to the plain package
+        assert_eq!(closure("app-1.0.0[linux]").unwrap(), vec!["bar-1.0.0", "foo-1.2.0[linux]"]);
+        assert_eq!(closure("app-1.0.0[gcc9,linux]").unwrap(), vec!["foo-1.2.0[linux]"]);
+        // a plain package cannot choose between several variants
+        assert_eq!(closure("app-1.0.0"), Err(VersionitisError::AmbiguousVariant(
+            "app-1.0.0 requires foo, but foo-1.2.0[linux] and foo-1.2.0[windows] are equally suited to app-1.0.0".to_string())));
+    }
+
+    #[test]
+    fn closure_ignores_variants_for_other_platforms() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(manifest("app-1.0.0[linux]", Phase::Run, &[("foo", "1.0.0<2.0.0")]));
+        repo.add(manifest("foo-1.2.0[windows]", Phase::Run, &[]));
+        repo.add(manifest("foo-1.1.0[linux]", Phase::Run, &[]));
+
+        let closure = repo.closure(&Package::from_str("app-1.0.0[linux]").unwrap(), Phase::Run).unwrap()
+            .iter().map(|m| m.package().spec()).collect::<Vec<String>>();
+        assert_eq!(closure, vec!["foo-1.1.0[linux]"]);
+    }
+
+    #[test]
+    fn can_index_providers() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(provider("ocio_impl-1.0.0", "colormanagement-2.1"));
+        repo.add(provider("other_impl-3.0.0", "colormanagement-2.0"));
+        // replacing a manifest replaces its entries in the index
+        repo.add(provider("ocio_impl-1.0.0", "colormanagement-2.2"));
+
+        let providers = repo.providers("colormanagement").iter()
+            .map(|(p, m)| format!("{} by {}", p, m.package()))
+            .collect::<Vec<String>>();
+        assert_eq!(providers, vec![
+            "colormanagement-2.0 by other_impl-3.0.0",
+            "colormanagement-2.2 by ocio_impl-1.0.0"
+        ]);
+        assert!(repo.providers("ocio_impl").is_empty());
+    }
+
+    #[test]
+    fn closure_satisfies_virtual_dependencies_with_providers() {
+        let arena = ManifestArena::new();
+        let mut repo = ManifestRepo::new(&arena);
+        repo.add(manifest("app-1.0.0", Phase::Run, &[("colormanagement", "2.0<3.0")]));
+        repo.add(manifest("tool-1.0.0", Phase::Run, &[("colormanagement", "2.0<=2.0"), ("other_impl", "3.0.0")]));
+        repo.add(provider("ocio_impl-1.0.0", "colormanagement-2.1"));
+        repo.add(provider("other_impl-3.0.0", "colormanagement-2.0"));
+
+        let closure = repo.closure(&Package::from_str("app-1.0.0").unwrap(), Phase::Run).unwrap()
+            .iter().map(|m| m.package().spec()).collect::<Vec<String>>();
+        assert_eq!(closure, vec!["ocio_impl-1.0.0"]);
+
+        let closure = repo.closure(&Package::from_str("tool-1.0.0").unwrap(), Phase::Run).unwrap()
+            .iter().map(|m| m.package().spec()).collect::<Vec<String>>();
+        assert_eq!(closure, vec!["other_impl-3.0.0"]);
+    }
+
     #[test]
     fn can_load_from_disk() {
         let version = env!("CARGO_MANIFEST_DIR");
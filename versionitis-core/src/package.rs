@@ -1 +1,414 @@
-pub mod owned;
+//! package.rs
+//!
+//! The Package, a versioned name with an optional variant. There is a single
+//! Package type: its name, version and variant are interned, so a Package
+//! built from a borrowed str neither borrows from it nor copies it, and may be
+//! passed by value to intervals, maps and repos alike.
+
+use crate::{
+    errors::VersionitisError,
+    intern::{self, Name},
+    package_version_parser::PackageVersionParser,
+    version_number::VersionNumber,
+};
+use serde::{
+    de::{self, Deserializer, Visitor},
+    ser::{Serialize, Serializer},
+    Deserialize,
+};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+
+/// A named entity which is ordered, convertable to and from a
+/// string, hashable, and may of course be debuged.
+///
+/// A Package may carry a variant, a list of qualifiers distinguishing builds of
+/// the same version (eg foo-1.2.0[gcc9,linux] or foo-1.2.0[os=linux]). The
+/// variant takes part in identity, ordering and hashing. Qualifiers are kept
+/// sorted, so the order in which they are written does not matter.
+///
+/// A Package is a small Copy handle. Its name, version and variant are
+/// interned, so each is stored once however many packages share it.
+#[derive(PartialEq, PartialOrd, Eq, Ord, Hash, Clone, Copy)]
+pub struct Package {
+    name: Name,
+    version: VersionNumber,
+    variant: &'static [Name],
+}
+
+impl Serialize for Package {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("Package", &self.spec())
+    }
+}
+
+// PackageVisitor used for serde deserialization
+struct PackageVisitor;
+// Visitor implemented as part of custom serde pass
+impl<'de> Visitor<'de> for PackageVisitor {
+    type Value = Package;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a str of the form name-version[variant] (eg fred-0.1.0 or fred-0.1.0[linux])")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Package::from_str(value)
+            .map_err(|e| de::Error::custom(format!("unable to deserialize: {}", e)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Package {
+    fn deserialize<D>(deserializer: D) -> Result<Package, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(PackageVisitor)
+    }
+}
+
+impl fmt::Debug for Package {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Package {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.name, self.version)?;
+        if !self.variant.is_empty() {
+            write!(f, "[{}]", self.variant.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl Package {
+    /// Extract the package name as a &str
+    pub fn name(&self) -> &'static str {
+        self.name.as_str()
+    }
+
+    /// Retrieve the interned package name
+    pub fn interned_name(&self) -> Name {
+        self.name
+    }
+
+    /// Get the full specification for a package, in the form of "name-version",
+    /// or "name-version[variant]" for a variant, as a String
+    pub fn spec(&self) -> String {
+        self.to_string()
+    }
+
+    /// Get the version as a &str from the package
+    pub fn version(&self) -> String {
+        self.version.to_string()
+    }
+    pub fn version_number(&self) -> &VersionNumber {
+        &self.version
+    }
+
+    /// Retrieve the variant qualifiers, sorted. A package without a variant
+    /// returns an empty slice.
+    pub fn variant(&self) -> &'static [Name] {
+        self.variant
+    }
+
+    /// Test whether the package carries a variant
+    pub fn has_variant(&self) -> bool {
+        !self.variant.is_empty()
+    }
+
+    /// Test whether two packages share a name and version, regardless of variant
+    pub fn same_version(&self, other: &Package) -> bool {
+        self.name == other.name && self.version == other.version
+    }
+
+    /// Construct a Package from a name and a VersionNumber instance.
+    pub fn new<T: AsRef<str>>(name: T, version: VersionNumber) -> Self {
+        Self {
+            name: Name::new(name.as_ref()),
+            version,
+            variant: &[],
+        }
+    }
+
+    /// Replace the package's variant with the supplied qualifiers, which are
+    /// sorted and deduplicated.
+    pub fn with_variant<I, S>(mut self, qualifiers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut variant = qualifiers.into_iter().map(|q| Name::new(q.as_ref())).collect::<Vec<Name>>();
+        variant.sort();
+        variant.dedup();
+        self.variant = intern::variant(&variant);
+        self
+    }
+
+    /// Construct a Package from a package name and three u16 values,
+    /// following the semver spec.
+    pub fn semver(name: &str, major: u16, minor: u16, micro: u16) -> Self {
+        let value = VersionNumber::new(vec![major, minor, micro]);
+        Self::new(name, value)
+    }
+
+    /// Construct a Package from a package name, and four u16 values, following
+    /// the semver spec, plus a patch version to allow for context and manifest changes.
+    pub fn semver4(name: &str, major: u16, minor: u16, micro: u16, patch: u16) -> Self {
+        let value = VersionNumber::new(vec![major, minor, micro, patch]);
+        Self::new(name, value)
+    }
+
+    /// Parse a Package from a str. The str is
+    /// parsed by the PackageVersionParser, so the name must be valid and the
+    /// version must follow the last hyphen (eg fred-0.1.0). An optional variant
+    /// may follow the version (eg fred-0.1.0[linux,gcc9]).
+    // kept alongside the FromStr impl so that callers need not import the trait
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, VersionitisError> {
+        let (name, version, variant) = PackageVersionParser::parse_qualified(s)?;
+        Ok(Self::from_strs(name, version)?.with_variant(variant))
+    }
+
+    /// Construct a Package from a name and a version str (eg "fred", "0.1.0")
+    pub fn from_strs(name: &str, version: &str) -> Result<Self, VersionitisError> {
+        let mut result: Vec<u16> = Vec::new();
+        for x in version.split(".").map(|x| x.parse::<u16>()) {
+            let x = x?;
+            result.push(x);
+        }
+
+        Ok(Package::new(name, VersionNumber::new(result)))
+    }
+}
+
+impl FromStr for Package {
+    type Err = VersionitisError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Package::from_str(s)
+    }
+}
+
+impl TryFrom<&str> for Package {
+    type Error = VersionitisError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Package::from_str(s)
+    }
+}
+
+#[macro_export]
+macro_rules! version {
+    ($e:expr) => {
+        Package::from_str(
+            stringify!($e)
+                .chars()
+                .filter(|x| *x != ' ')
+                .collect::<String>()
+                .as_str(),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_implements_display_trait() {
+        let p = Package::from_str("foo-0.1.0").unwrap();
+        let pd = format!("{}", p);
+        assert_eq!(pd, "foo-0.1.0".to_string());
+    }
+
+    #[test]
+    fn can_create_package_from_name_with_spaces_using_verison_macro() {
+        let sv1 = version!(foo - 0.1.0);
+        let sv2 = Package::from_str("foo-0.1.0");
+        assert_eq!(sv1, sv2);
+    }
+
+    #[test]
+    fn can_create_package_using_from_str_function() {
+        let package = String::from("fred-0.1.0.1");
+        let sv1 = Package::from_str(&package).unwrap();
+        assert_eq!(sv1.name(), "fred");
+    }
+
+    #[test]
+    fn can_create_package_using_new_function_given_name_str_and_versionnumber() {
+        let name = String::from("fred");
+        let sv1 = Package::semver(&name, 0, 1, 0);
+        let sv2 = Package::new(name.as_str(), VersionNumber::new(vec![0, 1, 0]));
+        assert_eq!(sv1, sv2);
+    }
+
+    #[test]
+    fn packages_with_same_name_and_version_are_equal() {
+        let name = String::from("fred");
+        let sv1 = Package::semver(&name, 0, 1, 0);
+        let sv2 = Package::semver(&name, 0, 1, 0);
+        assert_eq!(sv1, sv2);
+    }
+
+    #[test]
+    fn package_with_same_name_and_small_version_less_than_other_package() {
+        let name = String::from("fred");
+        let sv1 = Package::semver(&name, 0, 0, 1);
+        let sv2 = Package::semver(&name, 0, 1, 0);
+        assert!(sv1 < sv2);
+    }
+
+    #[test]
+    fn package_with_higher_version_greater_than_package_with_smaller_version() {
+        let name = String::from("fred");
+        let sv1 = Package::semver(&name, 1, 0, 1);
+        let sv2 = Package::semver(&name, 0, 1, 0);
+        assert!(sv1 > sv2);
+    }
+
+    #[test]
+    fn package_with_more_digits_in_version_ord_test_but_zero() {
+        let name = String::from("fred");
+        let sv1 = Package::semver(&name, 0, 1, 0);
+        let sv2 = Package::semver4(&name, 0, 1, 0, 0);
+        assert!(sv1 < sv2);
+    }
+
+    #[test]
+    fn package_with_more_digits_in_version_ord_test_more_digits_gt() {
+        let name = String::from("fred");
+        let sv1 = Package::semver(&name, 0, 1, 0);
+        let sv2 = Package::semver4(&name, 0, 1, 0, 1);
+        assert!(sv1 < sv2);
+    }
+
+    #[test]
+    fn package_with_more_digits_in_version_ord_test_2() {
+        let name = String::from("fred");
+        let sv1 = Package::semver(&name, 0, 1, 1);
+        let sv2 = Package::semver4(&name, 0, 1, 0, 1);
+        assert!(sv1 > sv2);
+    }
+
+    #[test]
+    fn can_convert_to_string() {
+        let name = String::from("fred");
+        let sv2 = Package::semver4(&name, 0, 1, 0, 1);
+        assert_eq!(sv2.to_string().as_str(), "fred-0.1.0.1");
+    }
+
+    #[test]
+    fn pakage_implements_debug_trait() {
+        let name = String::from("fred");
+        let package = String::from("fred-0.1.0.1");
+        let sv = Package::semver4(&name, 0, 1, 0, 1);
+        let result = format!("{:?}", sv);
+        assert_eq!(result, package);
+    }
+
+    #[test]
+    fn can_convert_from_str() {
+        let name = String::from("fred");
+        let package = String::from("fred-0.1.0.1");
+        let sv1 = Package::from_str(&package).unwrap();
+        let sv2 = Package::semver4(&name, 0, 1, 0, 1);
+        assert_eq!(sv1, sv2);
+    }
+
+    const YAML_PKG: &'static str = "---\nfred-0.1.2";
+    #[test]
+    fn can_serialize_to_yaml() {
+        let package = Package::from_str("fred-0.1.2").unwrap();
+        let yaml = serde_yaml::to_string(&package).unwrap();
+        assert_eq!(yaml, YAML_PKG);
+    }
+
+    #[test]
+    fn can_deserialize_from_yaml() {
+        let package: serde_yaml::Result<Package> = serde_yaml::from_str("fred-0.1.2");
+        assert!(package.is_ok());
+    }
+
+    #[test]
+    fn from_str_rejects_malformed_packages() {
+        assert!(Package::from_str("fred").is_err());
+        assert!(Package::from_str("fred-").is_err());
+        assert!(Package::from_str("-0.1.0").is_err());
+    }
+
+    #[test]
+    fn can_convert_variant_from_str() {
+        let package = Package::from_str("foo-1.2.0[linux,gcc9]").unwrap();
+        assert_eq!(package.variant(), &["gcc9", "linux"]);
+        assert_eq!(package.spec(), "foo-1.2.0[gcc9,linux]");
+        assert_eq!(package, Package::from_str("foo-1.2.0[gcc9,linux]").unwrap());
+        assert_eq!(package, Package::semver("foo", 1, 2, 0).with_variant(vec!["linux", "gcc9"]));
+    }
+
+    #[test]
+    fn variant_takes_part_in_identity() {
+        let plain = Package::from_str("foo-1.2.0").unwrap();
+        let linux = Package::from_str("foo-1.2.0[linux]").unwrap();
+        let windows = Package::from_str("foo-1.2.0[windows]").unwrap();
+        assert_ne!(plain, linux);
+        assert_ne!(linux, windows);
+        assert!(plain < linux && linux < windows);
+        assert!(linux.same_version(&windows));
+        assert!(!plain.has_variant());
+    }
+
+    #[test]
+    fn can_round_trip_variant_through_yaml() {
+        let package = Package::from_str("foo-1.2.0[os=linux]").unwrap();
+        let yaml = serde_yaml::to_string(&package).unwrap();
+        let result: Package = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(package, result);
+    }
+
+    #[test]
+    fn deserializing_a_malformed_package_is_an_error() {
+        let package: serde_yaml::Result<Package> = serde_yaml::from_str("fred");
+        assert!(package.is_err());
+    }
+
+    #[test]
+    fn packages_built_from_borrowed_strs_outlive_them() {
+        let package = {
+            let spec = String::from("fred-0.1.0[linux]");
+            Package::try_from(spec.as_str()).unwrap()
+        };
+        assert_eq!(package.spec(), "fred-0.1.0[linux]");
+        assert_eq!("fred-0.1.0".parse::<Package>().unwrap(), Package::semver("fred", 0, 1, 0));
+    }
+
+    #[test]
+    fn packages_are_accepted_by_intervals_maps_and_repos() {
+        use crate::{interval::Interval, package_map::PackageMap, package_repo::PackageRepo, traits::TrackPackages};
+        let start = Package::semver("fred", 0, 1, 0);
+        let end = Package::semver("fred", 1, 0, 0);
+        let interval = Interval::half_open(start, end).unwrap();
+        assert!(interval.contains(&Package::semver("fred", 0, 5, 0)));
+
+        let mut map = PackageMap::new();
+        map.add(start);
+        assert!(map.has("fred-0.1.0"));
+
+        let mut repo = PackageRepo::new();
+        repo.add_package(start).unwrap();
+        repo.add_package(end).unwrap();
+        assert!(repo.add_package(start).is_err());
+        assert_eq!(repo.get("fred").unwrap(), &vec![start, end]);
+    }
+}
@@ -2,11 +2,12 @@
 //!
 //! map package versions to 1 based id, for use with SAT solver.
 //!
-use crate::package::owned::Package;
+use crate::package::Package;
 use std::collections::HashMap;
 
 type IdxType = i32;
-type PMap = HashMap<String, IdxType>;
+// Packages are interned handles, so keying by Package stores no strings
+type PMap = HashMap<Package, IdxType>;
 
 /// Store packages in a SAT friendly structure
 pub struct PackageMap {
@@ -25,17 +26,17 @@ impl PackageMap {
 
     /// add a Package to the map if it doesnt exist
     pub fn add(&mut self, version: Package) {
-        let name = version.spec().to_string();
-        if !self.has(name.as_str()) {
+        if !self.map.contains_key(&version) {
             self.arena.push(version);
-            self.map.insert(name, self.arena.len() as IdxType);
+            self.map.insert(version, self.arena.len() as IdxType);
         }
     }
 
     /// Given a package version str, determine whether the package map
-    /// contains the package version.
+    /// contains the package version. Variant qualifiers may be supplied in
+    /// any order.
     pub fn has(&self, version_str: &str) -> bool {
-        self.map.contains_key(version_str)
+        self.get(version_str).is_some()
     }
 
     /// given a &str representing a valid package name, create a Package
@@ -48,10 +49,8 @@ impl PackageMap {
 
     /// Retrieve an Option wrapping a reference to an IdxType
     pub fn get(&self, value: &str) -> Option<IdxType> {
-        match self.map.get(value) {
-            Some(value) => Some(*value),
-            None => None,
-        }
+        let package = Package::from_str(value).ok()?;
+        self.map.get(&package).copied()
     }
 
     /// Retrueve the Package associated with a particular literal. The literal
@@ -103,4 +102,16 @@ mod test {
         assert_eq!(idx, idx_after);
     }
 
+    #[test]
+    fn variants_are_distinct_literals() {
+        let mut mymap = PackageMap::new();
+        mymap.add_str("foo-0.1.0");
+        mymap.add_str("foo-0.1.0[linux]");
+        mymap.add_str("foo-0.1.0[windows,x64]");
+        assert_eq!(mymap.len(), 3);
+        assert_eq!(mymap.get("foo-0.1.0[linux]"), Some(2));
+        assert!(mymap.has("foo-0.1.0[x64,windows]"));
+        assert_eq!(mymap.at_lit(3).map(|p| p.spec()), Some("foo-0.1.0[windows,x64]".to_string()));
+    }
+
 }
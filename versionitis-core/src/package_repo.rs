@@ -2,18 +2,21 @@
 //!
 //! Store and retrieve package versions. The repo is intended to
 //! hold the available packages in memory.
-use crate::{errors::VersionitisError, package::owned::Package, traits::TrackPackages};
+use crate::{errors::VersionitisError, intern::Name, package::Package, traits::TrackPackages};
 use serde_derive::{Deserialize, Serialize};
 use std::{collections::HashMap, iter::Iterator};
 
-// type alias
-type PackageMap = HashMap<String, Vec<Package>>;
+// type alias. Keys are interned, sharing storage with the names of the
+// packages, and may be looked up by &str.
+type PackageMap = HashMap<Name, Vec<Package>>;
 
 /// The PackageRepo stores package versions for each package
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct PackageRepo {
-    pub packages: PackageMap,
+    // unchecked precedes the packages table, as toml requires
     unchecked: bool, // have we called add_version_nocheck
+    #[serde(serialize_with = "crate::canonical::serialize_sorted")]
+    pub packages: PackageMap,
 }
 
 impl PackageRepo {
@@ -32,7 +35,7 @@ impl PackageRepo {
             v.dedup();
             // TODO: do we need this here? only after deserialization. perhaps
             // i will put it there.
-            v.iter_mut().filter(|x| x.name() == key).for_each(|_| {});
+            v.iter_mut().filter(|x| x.interned_name() == *key).for_each(|_| {});
         });
         self.unchecked = false;
     }
@@ -48,6 +51,12 @@ impl PackageRepo {
         self.add_version_imp(package_name, version, false)
     }
 
+    /// Add a Package to the repository, verifying that its version is greater
+    /// than that of the last version added, as add_version does.
+    pub fn add_package(&mut self, package: Package) -> Result<(), VersionitisError> {
+        self.add_package_imp(package, true)
+    }
+
     /// Implementation for add_version and add_version_nocheck
     fn add_version_imp(
         &mut self,
@@ -55,13 +64,17 @@ impl PackageRepo {
         version: &str,
         check: bool,
     ) -> Result<(), VersionitisError> {
-        let pack = Package::from_strs(package_name, version)?;
+        self.add_package_imp(Package::from_strs(package_name, version)?, check)
+    }
+
+    /// Implementation for add_package, add_version and add_version_nocheck
+    fn add_package_imp(&mut self, pack: Package, check: bool) -> Result<(), VersionitisError> {
         // retrieve the vector of package versions for the supplied
         // package name. If it exists, verify that the new package's
         // version is greater than the version of the last package in
         // the aforementioned vector. Otherwise, return an InvalidPackageVersion
         // error.
-        match self.packages.get_mut(package_name) {
+        match self.packages.get_mut(pack.name()) {
             Some(ref mut lst) => {
                 if check {
                     if let Some(last_elem) = lst.last() {
@@ -80,7 +93,7 @@ impl PackageRepo {
             _ => {
                 // the package key does not exist. Create it and add a vec
                 // value which has the new versioned package.
-                self.packages.insert(package_name.to_string(), vec![pack]);
+                self.packages.insert(pack.interned_name(), vec![pack]);
                 Ok(())
             }
         }
@@ -124,14 +137,14 @@ impl TrackPackages for PackageRepo {
 mod tests {
     use super::*;
     const REPO: &'static str = r#"---
+unchecked: false
 packages:
   fred:
     - fred-0.1.0
     - fred-0.2.0
     - fred-0.2.1
     - fred-0.2.3
-    - fred-0.3.0
-unchecked: false"#;
+    - fred-0.3.0"#;
 
     fn setup_repo(package_name: &str) -> PackageRepo {
         let mut repo = PackageRepo::new();
@@ -53,13 +53,49 @@ impl PackageVersionParser {
             };
         }
 
-        if name.is_none() || version.is_none() {
-            return Err(VersionitisError::ParseError(
+        match (name, version) {
+            (Some(name), Some(version)) => Ok((name, version)),
+            _ => Err(VersionitisError::ParseError(
                 format!("unable to parse {} name.is_none:{} version.is_none:{}",
-                input, name.is_none(), version.is_none())));
+                input, name.is_none(), version.is_none()))),
+        }
+    }
+
+    /// Convert a str to a PackageVersion with an optional variant qualifier
+    /// (eg foo-1.2.3[linux,gcc9]), returning the name, version and qualifiers,
+    /// or a VersionitisError if not successful. A package without a variant
+    /// yields an empty list of qualifiers.
+    ///
+    /// # Example
+    /// ```
+    /// use versionitis::package_version_parser::PackageVersionParser;
+    /// let (name, version, variant) = PackageVersionParser::parse_qualified("foo-1.2.3[linux,gcc9]").unwrap();
+    /// assert_eq!(variant, vec!["linux", "gcc9"]);
+    /// ```
+    pub fn parse_qualified(input: &str) -> Result<(&str, &str, Vec<&str>), VersionitisError> {
+        let qualified = _PackageVersionParser::parse(Rule::qualified, input)
+            .map_err(|e| VersionitisError::ParseError(
+                format!("unable to parse: '{}' error: '{}'",input, e)
+            ))?;
+
+        let mut version = None;
+        let mut name = None;
+        let mut variant = Vec::new();
+        for piece in qualified.flatten() {
+            match piece.as_rule() {
+                Rule::version => version = Some(piece.as_str()),
+                Rule::name => name = Some(piece.as_str()),
+                Rule::qualifier => variant.push(piece.as_str()),
+                _ => {}
+            };
         }
 
-        return Ok((name.unwrap(), version.unwrap()))
+        match (name, version) {
+            (Some(name), Some(version)) => Ok((name, version, variant)),
+            _ => Err(VersionitisError::ParseError(
+                format!("unable to parse {} name.is_none:{} version.is_none:{}",
+                input, name.is_none(), version.is_none()))),
+        }
     }
 }
 
@@ -114,6 +150,22 @@ mod test {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn can_parse_variant_qualifiers() {
+        let result = PackageVersionParser::parse_qualified("foo-1.2.3[linux,gcc9,abi=cxx11]");
+        assert_eq!(result, Ok(("foo", "1.2.3", vec!["linux", "gcc9", "abi=cxx11"])));
+        let result = PackageVersionParser::parse_qualified("foo-1.2.3");
+        assert_eq!(result, Ok(("foo", "1.2.3", Vec::new())));
+    }
+
+    #[test]
+    fn variant_must_be_well_formed() {
+        assert!(PackageVersionParser::parse_qualified("foo-1.2.3[]").is_err());
+        assert!(PackageVersionParser::parse_qualified("foo-1.2.3[linux,]").is_err());
+        assert!(PackageVersionParser::parse_qualified("foo-1.2.3[linux").is_err());
+        assert!(PackageVersionParser::parse("foo-1.2.3[linux]").is_err());
+    }
+
     #[test]
     fn can_pass_in_string_reference() {
         let pkg = "foo_bar-1.2.3".to_string();
@@ -0,0 +1,166 @@
+//! properties.rs
+//!
+//! Property based tests of version number, package and interval semantics,
+//! along with proptest strategies generating each of them.
+//!
+use crate::{
+    interval::Interval,
+    package::Package,
+    vernum_interval_parser::VerNumIntervalParser,
+    version_number::VersionNumber,
+    version_number_interval::VersionNumberInterval,
+};
+use proptest::prelude::*;
+
+/// Generate version numbers of one to five digits. Digits are drawn mostly
+/// from a small range, so that generated versions frequently share prefixes
+/// and compare equal, with the occasional digit anywhere in the u16 range.
+pub(crate) fn version_number() -> impl Strategy<Value = VersionNumber> {
+    let digit = prop_oneof![4 => 0u16..4, 1 => any::<u16>()];
+    prop::collection::vec(digit, 1..=5).prop_map(VersionNumber::new)
+}
+
+/// Generate package names accepted by the package grammar: a letter, at least
+/// one more letter or digit, then any number of `_` separated pieces.
+pub(crate) fn package_name() -> impl Strategy<Value = String> {
+    "[a-zA-Z][a-zA-Z0-9]{1,6}(_[a-zA-Z0-9]{1,4}){0,2}"
+}
+
+/// Generate packages, a third of which carry a variant
+pub(crate) fn package() -> impl Strategy<Value = Package> {
+    let qualifier = "[a-z0-9_=.]{1,6}";
+    let variant = prop_oneof![
+        2 => Just(Vec::new()),
+        1 => prop::collection::vec(qualifier, 1..=3),
+    ];
+    (package_name(), version_number(), variant)
+        .prop_map(|(name, version, variant)| Package::new(name, version).with_variant(variant))
+}
+
+/// Generate valid intervals of every variant. Bounds are ordered so that the
+/// interval constructors accept them.
+pub(crate) fn interval() -> impl Strategy<Value = VersionNumberInterval> {
+    let ordered = || (version_number(), version_number()).prop_map(|(a, b)| if a <= b { (a, b) } else { (b, a) });
+    prop_oneof![
+        version_number().prop_map(Interval::Single),
+        ordered()
+            .prop_filter("half open bounds must differ", |(a, b)| a != b)
+            .prop_map(|(start, end)| Interval::half_open(start, end).unwrap()),
+        ordered().prop_map(|(start, end)| Interval::open(start, end).unwrap()),
+        Just(Interval::Empty),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn version_numbers_round_trip_through_display(version in version_number()) {
+        prop_assert_eq!(VersionNumber::from_str(&version.to_string()).unwrap(), version);
+    }
+
+    #[test]
+    fn version_numbers_round_trip_through_yaml(version in version_number()) {
+        let yaml = serde_yaml::to_string(&version).unwrap();
+        prop_assert_eq!(serde_yaml::from_str::<VersionNumber>(&yaml).unwrap(), version);
+    }
+
+    #[test]
+    fn version_number_order_is_that_of_its_digits(a in version_number(), b in version_number()) {
+        prop_assert_eq!(a.cmp(&b), a.digits().cmp(b.digits()));
+        prop_assert_eq!(a == b, a.digits() == b.digits());
+    }
+
+    #[test]
+    fn version_number_order_is_transitive(a in version_number(), b in version_number(), c in version_number()) {
+        let mut sorted = [a, b, c];
+        sorted.sort();
+        prop_assert!(sorted[0] <= sorted[1] && sorted[1] <= sorted[2]);
+        prop_assert!(sorted[0] <= sorted[2]);
+        if a < b && b < c {
+            prop_assert!(a < c);
+        }
+    }
+
+    #[test]
+    fn version_numbers_with_empty_components_are_rejected(
+        digits in prop::collection::vec(any::<u16>(), 0..4),
+        position in 0usize..4,
+    ) {
+        let mut components = digits.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        components.insert(position.min(components.len()), String::new());
+        prop_assert!(VersionNumber::from_str(&components.join(".")).is_err());
+    }
+
+    #[test]
+    fn parsed_version_numbers_are_canonical(s in "[0-9.]{0,12}") {
+        if let Ok(version) = VersionNumber::from_str(&s) {
+            prop_assert_eq!(VersionNumber::from_str(&version.to_string()).unwrap(), version);
+        }
+    }
+
+    #[test]
+    fn packages_round_trip_through_spec(package in package()) {
+        prop_assert_eq!(Package::from_str(&package.spec()).unwrap(), package);
+    }
+
+    #[test]
+    fn packages_round_trip_through_yaml(package in package()) {
+        let yaml = serde_yaml::to_string(&package).unwrap();
+        prop_assert_eq!(serde_yaml::from_str::<Package>(&yaml).unwrap(), package);
+    }
+
+    #[test]
+    fn package_order_is_transitive(a in package(), b in package(), c in package()) {
+        if a <= b && b <= c {
+            prop_assert!(a <= c);
+        }
+        prop_assert_eq!(a == b, a.cmp(&b) == std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn intervals_round_trip_through_to_range(interval in interval()) {
+        prop_assert_eq!(VerNumIntervalParser::parse(&interval.to_range()).unwrap(), interval.clone());
+        prop_assert_eq!(VersionNumberInterval::from_str(&interval.to_string()).unwrap(), interval);
+    }
+
+    #[test]
+    fn intervals_round_trip_through_yaml(interval in interval()) {
+        let yaml = serde_yaml::to_string(&interval).unwrap();
+        prop_assert_eq!(serde_yaml::from_str::<VersionNumberInterval>(&yaml).unwrap(), interval);
+    }
+
+    #[test]
+    fn contains_agrees_with_bounds(interval in interval(), version in version_number()) {
+        let expected = match interval {
+            Interval::Single(v) => version == v,
+            Interval::HalfOpen { start, end } => start <= version && version < end,
+            Interval::Open { start, end } => start <= version && version <= end,
+            Interval::Empty => false,
+        };
+        prop_assert_eq!(interval.contains(&version), expected);
+        if expected {
+            prop_assert!(!interval.is_empty());
+        }
+    }
+
+    #[test]
+    fn intervals_contain_their_bounds(interval in interval()) {
+        match interval {
+            Interval::Single(v) => prop_assert!(interval.contains(&v)),
+            Interval::HalfOpen { start, end } => {
+                prop_assert!(interval.contains(&start));
+                prop_assert!(!interval.contains(&end));
+            }
+            Interval::Open { start, end } => {
+                prop_assert!(interval.contains(&start));
+                prop_assert!(interval.contains(&end));
+            }
+            Interval::Empty => prop_assert!(interval.is_empty()),
+        }
+    }
+
+    #[test]
+    fn intersection_contains_values_common_to_both(a in interval(), b in interval(), version in version_number()) {
+        let both = a.contains(&version) && b.contains(&version);
+        prop_assert_eq!(a.intersection(&b).contains(&version), both);
+    }
+}
@@ -0,0 +1,240 @@
+//! schema.rs
+//!
+//! Manifest schema versions, and the migrations which upgrade documents
+//! written in older versions of the schema.
+//!
+use crate::{
+    errors::VersionitisError,
+    interval_map::IntervalMap,
+    manifest::{Manifest, Phase},
+    package::Package,
+};
+use serde::{Deserialize, Deserializer};
+use serde_derive::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::convert::TryFrom;
+
+/// The schema version of manifests written before `schema_version` existed.
+/// A version 1 manifest has a `name` and run `dependencies` only.
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// The schema version written by this library. Version 2 adds dependency
+/// phases, conflicts, provides, optional and conditional dependencies,
+/// `extends` and package variants.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// The key under which a manifest records its schema version
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+// A migration upgrades a document from one schema version to the next.
+// MIGRATIONS[n] upgrades version n + 1 to version n + 2.
+type Migration = fn(&mut Mapping) -> Result<(), VersionitisError>;
+const MIGRATIONS: [Migration; 1] = [migrate_v1_to_v2];
+
+// Version 2 only adds sections, so a version 1 document is also a valid
+// version 2 document.
+fn migrate_v1_to_v2(_document: &mut Mapping) -> Result<(), VersionitisError> {
+    Ok(())
+}
+
+// Used as the serde default for Manifest::schema_version. Manifests are always
+// held in the current schema, whatever version they were read from.
+pub(crate) fn current_schema_version() -> u32 {
+    CURRENT_SCHEMA_VERSION
+}
+
+// Used as the serde deserializer for Manifest::schema_version, rejecting
+// documents from the future and upgrading the rest.
+pub(crate) fn deserialize_schema_version<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let version = u32::deserialize(deserializer)?;
+    check_version(version).map_err(serde::de::Error::custom)?;
+    Ok(CURRENT_SCHEMA_VERSION)
+}
+
+// verify that a schema version is one which this library understands
+fn check_version(version: u32) -> Result<(), VersionitisError> {
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(VersionitisError::UnsupportedSchemaVersion(format!(
+            "schema version {} is newer than the newest supported version, {}. Upgrade versionitis to read it",
+            version, CURRENT_SCHEMA_VERSION
+        )));
+    }
+    if version < LEGACY_SCHEMA_VERSION {
+        return Err(VersionitisError::UnsupportedSchemaVersion(format!(
+            "schema version {} is not a valid schema version", version
+        )));
+    }
+    Ok(())
+}
+
+/// Retrieve the schema version of a manifest document. Documents without a
+/// `schema_version` are version 1.
+pub fn schema_version(document: &Value) -> Result<u32, VersionitisError> {
+    match document.get(SCHEMA_VERSION_KEY) {
+        None => Ok(LEGACY_SCHEMA_VERSION),
+        Some(version) => {
+            let version = version.as_u64().ok_or_else(|| VersionitisError::UnsupportedSchemaVersion(format!(
+                "{} must be a positive integer", SCHEMA_VERSION_KEY
+            )))?;
+            // a version too large for a u32 is from the future too
+            u32::try_from(version).map_err(|_| VersionitisError::UnsupportedSchemaVersion(format!(
+                "schema version {} is newer than the newest supported version, {}. Upgrade versionitis to read it",
+                version, CURRENT_SCHEMA_VERSION
+            )))
+        }
+    }
+}
+
+/// Upgrade a manifest document to the current schema version, applying each
+/// migration in turn. Returns an error for documents from the future.
+pub fn upgrade(mut document: Value) -> Result<Value, VersionitisError> {
+    let version = schema_version(&document)?;
+    check_version(version)?;
+    let mapping = document.as_mapping_mut().ok_or_else(|| {
+        VersionitisError::ParseError("manifest document is not a mapping".to_string())
+    })?;
+    for migration in &MIGRATIONS[(version - LEGACY_SCHEMA_VERSION) as usize..] {
+        migration(mapping)?;
+    }
+    mapping.insert(SCHEMA_VERSION_KEY.into(), CURRENT_SCHEMA_VERSION.into());
+    Ok(document)
+}
+
+/// Load a manifest from yaml written in any supported schema version
+pub fn load_manifest(yaml: &str) -> Result<Manifest, VersionitisError> {
+    let document: Value = serde_yaml::from_str(yaml)?;
+    Ok(serde_yaml::from_value(upgrade(document)?)?)
+}
+
+/// Serialize a manifest as yaml in the chosen schema version, returning an
+/// error if the version is unsupported, or if the manifest uses features which
+/// the version cannot express.
+///
+/// # Example
+///
+/// ```
+/// use versionitis::{manifest::Manifest, schema::serialize_manifest};
+/// use std::str::FromStr;
+/// let yaml = serialize_manifest(&Manifest::from_str("foo-1.0.0").unwrap(), 1).unwrap();
+/// assert_eq!(yaml, "---\nname: foo-1.0.0\ndependencies: {}\n");
+/// ```
+pub fn serialize_manifest(manifest: &Manifest, version: u32) -> Result<String, VersionitisError> {
+    check_version(version)?;
+    if version == CURRENT_SCHEMA_VERSION {
+        return crate::canonical::to_canonical_string(manifest);
+    }
+    let unsupported = v2_features(manifest);
+    if !unsupported.is_empty() {
+        return Err(VersionitisError::UnsupportedSchemaVersion(format!(
+            "{} uses {}, which schema version {} does not support",
+            manifest.package(), unsupported.join(", "), version
+        )));
+    }
+    crate::canonical::to_canonical_string(&ManifestV1 {
+        name: manifest.package(),
+        dependencies: manifest.dependencies(),
+    })
+}
+
+// The layout of a version 1 manifest
+#[derive(Serialize)]
+struct ManifestV1<'a> {
+    name: &'a Package,
+    dependencies: &'a IntervalMap,
+}
+
+// list the features of a manifest which version 1 of the schema lacks
+fn v2_features(manifest: &Manifest) -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if Phase::ALL.iter().any(|phase| *phase != Phase::Run && !manifest.dependencies_for(*phase).is_empty()) {
+        features.push("dependency phases");
+    }
+    if !manifest.conflicts().is_empty() {
+        features.push("conflicts");
+    }
+    if !manifest.provides().is_empty() {
+        features.push("provides");
+    }
+    if !manifest.optional().is_empty() || !manifest.conditional().is_empty() {
+        features.push("optional dependencies");
+    }
+    if manifest.extends().is_some() {
+        features.push("extends");
+    }
+    if manifest.package().has_variant() {
+        features.push("variants");
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest_builder::ManifestBuilder;
+
+    const V1: &str = "---\nname: foo-1.0.0\ndependencies:\n  bar: '0.1.0<1.0.0'\n";
+
+    #[test]
+    fn documents_without_a_version_are_legacy() {
+        let document: Value = serde_yaml::from_str(V1).unwrap();
+        assert_eq!(schema_version(&document), Ok(LEGACY_SCHEMA_VERSION));
+        let upgraded = upgrade(document).unwrap();
+        assert_eq!(schema_version(&upgraded), Ok(CURRENT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn can_load_legacy_manifests() {
+        let manifest = load_manifest(V1).unwrap();
+        assert!(manifest.depends_on("bar"));
+        assert_eq!(manifest.schema_version(), CURRENT_SCHEMA_VERSION);
+        let manifest: Manifest = serde_yaml::from_str(V1).unwrap();
+        assert_eq!(manifest.schema_version(), CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn documents_from_the_future_are_rejected() {
+        let yaml = "schema_version: 3\nname: foo-1.0.0\ndependencies: {}\n";
+        assert_eq!(
+            load_manifest(yaml),
+            Err(VersionitisError::UnsupportedSchemaVersion(
+                "schema version 3 is newer than the newest supported version, 2. Upgrade versionitis to read it".to_string()
+            ))
+        );
+        let result: serde_yaml::Result<Manifest> = serde_yaml::from_str(yaml);
+        assert!(result.unwrap_err().to_string().contains("schema version 3 is newer"));
+    }
+
+    #[test]
+    fn versions_beyond_u32_are_rejected() {
+        let yaml = "schema_version: 4294967297\nname: foo-1.0.0\ndependencies: {}\n";
+        assert_eq!(
+            load_manifest(yaml),
+            Err(VersionitisError::UnsupportedSchemaVersion(
+                "schema version 4294967297 is newer than the newest supported version, 2. Upgrade versionitis to read it".to_string()
+            ))
+        );
+        assert!(crate::format::Format::Yaml.parse_manifest(yaml).is_err());
+    }
+
+    #[test]
+    fn can_write_in_a_chosen_version() {
+        let manifest = load_manifest(V1).unwrap();
+        assert_eq!(serialize_manifest(&manifest, 1).unwrap(), "---\nname: foo-1.0.0\ndependencies:\n  bar: 0.1.0<1.0.0\n");
+        assert_eq!(
+            serialize_manifest(&manifest, 2).unwrap(),
+            "---\nschema_version: 2\nname: foo-1.0.0\ndependencies:\n  bar: 0.1.0<1.0.0\n"
+        );
+        assert!(serialize_manifest(&manifest, 3).is_err());
+
+        let manifest = ManifestBuilder::new("foo-1.0.0").conflicts("bar", "0.1.0").build().unwrap();
+        assert_eq!(
+            serialize_manifest(&manifest, 1),
+            Err(VersionitisError::UnsupportedSchemaVersion(
+                "foo-1.0.0 uses conflicts, which schema version 1 does not support".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,615 @@
+//! snapshot.rs
+//!
+//! Compile a PackageRepo and a directory of manifests into a single binary
+//! snapshot which loads without parsing, and which knows when it is stale.
+//!
+use crate::{
+    errors::VersionitisError,
+    format::Format,
+    inheritance::{ManifestResolver, TEMPLATE_DIR},
+    intern,
+    interval::Interval,
+    interval_map::IntervalMap,
+    manifest::{Manifest, Phase},
+    manifest_repo::{ManifestArena, ManifestRepo},
+    package::Package,
+    package_repo::PackageRepo,
+    version_number::VersionNumber,
+    version_number_interval::VersionNumberInterval,
+};
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The bytes with which every snapshot file begins
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"VRSNSNAP";
+
+/// The version of the snapshot layout. Snapshots written with a different
+/// layout are rejected, and should be recompiled.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// A source file from which a snapshot was compiled, along with the size,
+/// modification time and content hash it had at the time.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SourceFile {
+    path: PathBuf,
+    size: u64,
+    mtime: (u64, u32),
+    hash: u64,
+}
+
+impl SourceFile {
+    /// Record the current state of a file, returning the contents from which
+    /// its hash was taken, so that what is recorded matches what is parsed
+    pub fn read<P: Into<PathBuf>>(path: P) -> Result<(Self, String), VersionitisError> {
+        let path = path.into();
+        let (size, mtime) = size_and_mtime(&path)?;
+        let contents = fs::read_to_string(&path)?;
+        let hash = fnv1a(contents.as_bytes());
+        Ok((Self { path, size, mtime, hash }, contents))
+    }
+
+    /// Retrieve the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Retrieve the hash of the file's contents
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Test whether the file's size and modification time are unchanged,
+    /// without reading it
+    pub fn metadata_matches(&self) -> bool {
+        match size_and_mtime(&self.path) {
+            Ok((size, mtime)) => size == self.size && mtime == self.mtime,
+            Err(_) => false,
+        }
+    }
+
+    /// Test whether the file is unchanged. A file whose size and modification
+    /// time are unchanged is assumed to be unchanged. Otherwise its contents
+    /// are hashed, so that touching a file does not invalidate a snapshot.
+    pub fn is_unchanged(&self) -> bool {
+        match size_and_mtime(&self.path) {
+            Ok((size, mtime)) if size == self.size && mtime == self.mtime => true,
+            Ok((size, _)) if size == self.size => {
+                fs::read(&self.path).map(|bytes| fnv1a(&bytes) == self.hash).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+}
+
+// retrieve the size and modification time of a file
+fn size_and_mtime(path: &Path) -> Result<(u64, (u64, u32)), VersionitisError> {
+    let metadata = fs::metadata(path)?;
+    let mtime = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| (d.as_secs(), d.subsec_nanos()))
+        .unwrap_or((0, 0));
+    Ok((metadata.len(), mtime))
+}
+
+// 64 bit FNV-1a, which unlike the std hashers is stable from release to release
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// The compact representations below refer to strings by their index in the
+// snapshot's table of names. This is unrelated to intern::Name, the handle to
+// a name interned for the life of the process.
+type NameIndex = u32;
+type CompactVersion = Vec<u16>;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CompactPackage {
+    name: NameIndex,
+    version: CompactVersion,
+    variant: Vec<NameIndex>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum CompactInterval {
+    Single(CompactVersion),
+    HalfOpen(CompactVersion, CompactVersion),
+    Open(CompactVersion, CompactVersion),
+    Empty,
+}
+
+impl CompactInterval {
+    // versions compare as their digits do, so the order may be checked
+    // without building VersionNumbers
+    fn is_well_formed(&self) -> bool {
+        match self {
+            CompactInterval::HalfOpen(start, end) => start < end,
+            CompactInterval::Open(start, end) => start <= end,
+            _ => true,
+        }
+    }
+}
+
+type CompactIntervalMap = Vec<(NameIndex, CompactInterval)>;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CompactManifest {
+    package: CompactPackage,
+    extends: Option<NameIndex>,
+    provides: Vec<CompactPackage>,
+    // indexed in the order of Phase::ALL
+    dependencies: Vec<CompactIntervalMap>,
+    conflicts: CompactIntervalMap,
+    optional: Vec<(NameIndex, CompactIntervalMap)>,
+    conditional: Vec<(Vec<(NameIndex, NameIndex)>, CompactIntervalMap)>,
+}
+
+/// A compiled snapshot of a PackageRepo and a ManifestRepo. Each name is stored
+/// once, in a table, and versions and intervals are stored pre-parsed, so that loading a snapshot
+/// involves no parsing of yaml or intervals. Manifests are stored flattened.
+/// A snapshot is read with a single read of the file rather than mmapped, which
+/// keeps the crate free of unsafe code.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    package_repo_path: PathBuf,
+    manifest_dir: PathBuf,
+    sources: Vec<SourceFile>,
+    names: Vec<String>,
+    packages: Vec<(NameIndex, Vec<CompactPackage>)>,
+    manifests: Vec<CompactManifest>,
+}
+
+// Assigns each string an index in the table of names while a snapshot is compiled
+#[derive(Default)]
+struct NameTable {
+    names: Vec<String>,
+    indices: HashMap<String, NameIndex>,
+}
+
+impl NameTable {
+    fn index(&mut self, name: &str) -> NameIndex {
+        if let Some(idx) = self.indices.get(name) {
+            return *idx;
+        }
+        let idx = self.names.len() as NameIndex;
+        self.names.push(name.to_string());
+        self.indices.insert(name.to_string(), idx);
+        idx
+    }
+
+    fn package(&mut self, package: &Package) -> CompactPackage {
+        CompactPackage {
+            name: self.index(package.name()),
+            version: package.version_number().value(),
+            variant: package.variant().iter().map(|q| self.index(q)).collect(),
+        }
+    }
+
+    fn interval_map(&mut self, map: &IntervalMap) -> CompactIntervalMap {
+        let mut entries = map.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+            .into_iter()
+            .map(|(name, interval)| {
+                let interval = match interval {
+                    Interval::Single(v) => CompactInterval::Single(v.value()),
+                    Interval::HalfOpen { start, end } => CompactInterval::HalfOpen(start.value(), end.value()),
+                    Interval::Open { start, end } => CompactInterval::Open(start.value(), end.value()),
+                    Interval::Empty => CompactInterval::Empty,
+                };
+                (self.index(name), interval)
+            })
+            .collect()
+    }
+
+    fn manifest(&mut self, manifest: &Manifest) -> CompactManifest {
+        let mut optional = manifest.optional().iter().collect::<Vec<_>>();
+        optional.sort_by(|a, b| a.0.cmp(b.0));
+        CompactManifest {
+            package: self.package(manifest.package()),
+            extends: manifest.extends().map(|parent| self.index(parent)),
+            provides: manifest.provides().iter().map(|p| self.package(p)).collect(),
+            dependencies: Phase::ALL
+                .iter()
+                .map(|phase| self.interval_map(manifest.dependencies_for(*phase)))
+                .collect(),
+            conflicts: self.interval_map(manifest.conflicts()),
+            optional: optional
+                .into_iter()
+                .map(|(feature, deps)| (self.index(feature), self.interval_map(deps)))
+                .collect(),
+            conditional: manifest
+                .conditional()
+                .iter()
+                .map(|c| {
+                    let mut when = c.when().iter().collect::<Vec<_>>();
+                    when.sort();
+                    let when = when.into_iter().map(|(k, v)| (self.index(k), self.index(v))).collect();
+                    (when, self.interval_map(c.dependencies()))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Compile a snapshot from a PackageRepo file and a directory of manifests,
+    /// recording the state of every source file. Each file is read once, and
+    /// the state recorded is that of the contents which were parsed.
+    pub fn compile<P: Into<PathBuf>, Q: Into<PathBuf>>(package_repo_path: P, manifest_dir: Q) -> Result<Self, VersionitisError> {
+        let package_repo_path = package_repo_path.into();
+        let manifest_dir = manifest_dir.into();
+        if !manifest_dir.is_dir() {
+            return Err(VersionitisError::IoError(format!("path: {:?} does not exist", manifest_dir)));
+        }
+
+        let (source, contents) = SourceFile::read(package_repo_path.clone())?;
+        let packages: PackageRepo = Format::from_path(&package_repo_path)?.parse(&contents)?;
+        let mut sources = vec![source];
+        let read = source_paths(&manifest_dir)?
+            .into_par_iter()
+            .map(SourceFile::read)
+            .collect::<Vec<_>>();
+        let mut files = Vec::with_capacity(read.len());
+        for file in read {
+            let (source, contents) = file?;
+            files.push((source.path.clone(), contents));
+            sources.push(source);
+        }
+
+        let arena = ManifestArena::new();
+        let mut manifests = ManifestRepo::new(&arena);
+        for manifest in ManifestResolver::from_contents(&manifest_dir, &files)?.resolve_all()? {
+            manifests.add(manifest);
+        }
+        Ok(Self::build(package_repo_path, manifest_dir, sources, &packages, &manifests))
+    }
+
+    // compile a snapshot from loaded repos
+    fn build(
+        package_repo_path: PathBuf,
+        manifest_dir: PathBuf,
+        sources: Vec<SourceFile>,
+        packages: &PackageRepo,
+        manifests: &ManifestRepo,
+    ) -> Self {
+        let mut table = NameTable::default();
+        let mut names = packages.packages.keys().collect::<Vec<_>>();
+        names.sort();
+        let packages = names
+            .into_iter()
+            .map(|name| {
+                let versions = packages.packages[name].iter().map(|p| table.package(p)).collect();
+                (table.index(name), versions)
+            })
+            .collect();
+        let mut keys = manifests.keys().collect::<Vec<_>>();
+        keys.sort();
+        let manifests = keys
+            .into_iter()
+            .map(|key| table.manifest(manifests.get_package(key).unwrap()))
+            .collect();
+        Self {
+            package_repo_path,
+            manifest_dir,
+            sources,
+            names: table.names,
+            packages,
+            manifests,
+        }
+    }
+
+    /// Write the snapshot to a file
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), VersionitisError> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend_from_slice(&SNAPSHOT_FORMAT_VERSION.to_le_bytes());
+        bytes.extend(bincode::serialize(self)?);
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Read a snapshot from a file, returning an InvalidSnapshot error if the
+    /// file is not a snapshot, was written with a different layout, or refers
+    /// to names or intervals which it does not hold.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, VersionitisError> {
+        let bytes = fs::read(path)?;
+        let header = SNAPSHOT_MAGIC.len() + 4;
+        if bytes.len() < header || &bytes[..SNAPSHOT_MAGIC.len()] != SNAPSHOT_MAGIC {
+            return Err(VersionitisError::InvalidSnapshot("not a versionitis snapshot".to_string()));
+        }
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&bytes[SNAPSHOT_MAGIC.len()..header]);
+        let version = u32::from_le_bytes(version);
+        if version != SNAPSHOT_FORMAT_VERSION {
+            return Err(VersionitisError::InvalidSnapshot(format!(
+                "snapshot format version {} does not match {}",
+                version, SNAPSHOT_FORMAT_VERSION
+            )));
+        }
+        let snapshot: Self = bincode::deserialize(&bytes[header..])?;
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    /// Read the snapshot at `path` if it is fresh and was compiled from the
+    /// supplied sources. Otherwise compile a new snapshot and write it to `path`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let snapshot = Snapshot::load_or_compile("repo.snap", "repo.yaml", "manifests")?;
+    /// let packages = snapshot.package_repo()?;
+    /// let arena = ManifestArena::new();
+    /// let manifests = snapshot.manifest_repo(&arena)?;
+    /// ```
+    pub fn load_or_compile<S, P, Q>(path: S, package_repo_path: P, manifest_dir: Q) -> Result<Self, VersionitisError>
+    where
+        S: AsRef<Path>,
+        P: Into<PathBuf>,
+        Q: Into<PathBuf>,
+    {
+        let package_repo_path = package_repo_path.into();
+        let manifest_dir = manifest_dir.into();
+        if let Ok(snapshot) = Self::read(&path) {
+            if snapshot.package_repo_path == package_repo_path
+                && snapshot.manifest_dir == manifest_dir
+                && snapshot.is_fresh()?
+            {
+                return Ok(snapshot);
+            }
+        }
+        let snapshot = Self::compile(package_repo_path, manifest_dir)?;
+        snapshot.write(path)?;
+        Ok(snapshot)
+    }
+
+    /// Test whether every source file is unchanged, and no manifest has been
+    /// added to or removed from the manifest directory.
+    pub fn is_fresh(&self) -> Result<bool, VersionitisError> {
+        if !self.sources.iter().all(|source| source.is_unchanged()) {
+            return Ok(false);
+        }
+        let mut current = source_paths(&self.manifest_dir)?;
+        current.push(self.package_repo_path.clone());
+        current.sort();
+        let mut recorded = self.sources.iter().map(|s| s.path.clone()).collect::<Vec<_>>();
+        recorded.sort();
+        Ok(current == recorded)
+    }
+
+    /// Retrieve the source files recorded by the snapshot
+    pub fn sources(&self) -> &[SourceFile] {
+        &self.sources
+    }
+
+    /// Reconstruct the PackageRepo
+    pub fn package_repo(&self) -> Result<PackageRepo, VersionitisError> {
+        let mut repo = PackageRepo::new();
+        for (name, versions) in &self.packages {
+            let versions = versions.iter().map(|p| self.package(p)).collect::<Result<_, _>>()?;
+            repo.packages.insert(intern::Name::new(self.name(*name)?), versions);
+        }
+        Ok(repo)
+    }
+
+    /// Reconstruct the ManifestRepo, allocating manifests in the supplied arena
+    pub fn manifest_repo<'a, 'b>(&self, arena: &'b ManifestArena) -> Result<ManifestRepo<'a, 'b>, VersionitisError> {
+        let mut repo = ManifestRepo::new(arena);
+        for manifest in &self.manifests {
+            repo.add(self.manifest(manifest)?);
+        }
+        Ok(repo)
+    }
+
+    // check that every name index lies within the table of names, and that no
+    // interval is inverted, so that a corrupt snapshot which still decodes is
+    // rejected, and recompiled, rather than trusted
+    fn validate(&self) -> Result<(), VersionitisError> {
+        let name = |name: &NameIndex| (*name as usize) < self.names.len();
+        let package = |p: &CompactPackage| name(&p.name) && p.variant.iter().all(name);
+        let map = |map: &CompactIntervalMap| map.iter().all(|(n, interval)| name(n) && interval.is_well_formed());
+        let packages = self.packages.iter().all(|(n, versions)| name(n) && versions.iter().all(package));
+        let manifests = self.manifests.iter().all(|m| {
+            package(&m.package)
+                && m.extends.iter().all(name)
+                && m.provides.iter().all(package)
+                && m.dependencies.len() == Phase::ALL.len()
+                && m.dependencies.iter().all(map)
+                && map(&m.conflicts)
+                && m.optional.iter().all(|(feature, deps)| name(feature) && map(deps))
+                && m.conditional.iter().all(|(when, deps)| when.iter().all(|(k, v)| name(k) && name(v)) && map(deps))
+        });
+        if packages && manifests {
+            Ok(())
+        } else {
+            Err(VersionitisError::InvalidSnapshot("snapshot is corrupt".to_string()))
+        }
+    }
+
+    fn name(&self, name: NameIndex) -> Result<&str, VersionitisError> {
+        self.names
+            .get(name as usize)
+            .map(|name| name.as_str())
+            .ok_or_else(|| VersionitisError::InvalidSnapshot(format!("name index {} is out of range", name)))
+    }
+
+    fn package(&self, package: &CompactPackage) -> Result<Package, VersionitisError> {
+        let variant = package.variant.iter().map(|q| self.name(*q)).collect::<Result<Vec<_>, _>>()?;
+        Ok(Package::new(self.name(package.name)?, VersionNumber::new(package.version.clone())).with_variant(variant))
+    }
+
+    fn interval_map(&self, map: &[(NameIndex, CompactInterval)]) -> Result<Vec<(String, VersionNumberInterval)>, VersionitisError> {
+        let version = |v: &CompactVersion| VersionNumber::new(v.clone());
+        map.iter()
+            .map(|(name, interval)| {
+                let interval = match interval {
+                    CompactInterval::Single(v) => Ok(Interval::Single(version(v))),
+                    CompactInterval::HalfOpen(start, end) => Interval::half_open(version(start), version(end)),
+                    CompactInterval::Open(start, end) => Interval::open(version(start), version(end)),
+                    CompactInterval::Empty => Ok(Interval::Empty),
+                };
+                let interval = interval.map_err(|err| VersionitisError::InvalidSnapshot(err.to_string()))?;
+                Ok((self.name(*name)?.to_string(), interval))
+            })
+            .collect()
+    }
+
+    fn manifest(&self, compact: &CompactManifest) -> Result<Manifest, VersionitisError> {
+        let mut manifest = Manifest::new(self.package(&compact.package)?);
+        manifest.set_extends(compact.extends.map(|parent| self.name(parent)).transpose()?);
+        for provided in &compact.provides {
+            manifest.add_provides(self.package(provided)?)?;
+        }
+        for (phase, dependencies) in Phase::ALL.iter().zip(&compact.dependencies) {
+            for (name, interval) in self.interval_map(dependencies)? {
+                manifest.add_dependency_for(*phase, name, interval)?;
+            }
+        }
+        for (name, interval) in self.interval_map(&compact.conflicts)? {
+            manifest.add_conflict(name, interval)?;
+        }
+        for (feature, dependencies) in &compact.optional {
+            for (name, interval) in self.interval_map(dependencies)? {
+                manifest.add_optional_dependency(self.name(*feature)?, name, interval)?;
+            }
+        }
+        for (when, dependencies) in &compact.conditional {
+            let when = when
+                .iter()
+                .map(|(k, v)| Ok((self.name(*k)?.to_string(), self.name(*v)?.to_string())))
+                .collect::<Result<HashMap<String, String>, VersionitisError>>()?;
+            for (name, interval) in self.interval_map(dependencies)? {
+                manifest.add_conditional_dependency(when.clone(), name, interval)?;
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+// list the manifest and template files which ManifestRepo::from_disk reads
+pub(crate) fn source_paths(manifest_dir: &Path) -> Result<Vec<PathBuf>, VersionitisError> {
+    let mut paths = Vec::new();
+    for dir in &[manifest_dir.to_path_buf(), manifest_dir.join(TEMPLATE_DIR)] {
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() && Format::from_path(&path).is_ok() {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{format, test_util::ScratchDir, traits::TrackPackages};
+
+    fn test_resources(name: &str) -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_resources");
+        path.push(name);
+        path
+    }
+
+    // copy a directory of test resources into a scratch directory
+    fn scratch(name: &str) -> ScratchDir {
+        let dir = ScratchDir::new(name);
+        dir.copy_from(test_resources("manifest_repo"), "manifests");
+        let mut repo = PackageRepo::new();
+        for (name, version) in &[("foo", "0.1.0"), ("foo", "1.0.0"), ("bar", "0.1.0"), ("abc", "0.1.0"), ("bla", "0.2.0"), ("bla", "0.3.0")] {
+            repo.add_version(name, version).unwrap();
+        }
+        format::save(dir.join("repo.yaml"), &repo).unwrap();
+        dir
+    }
+
+    #[test]
+    fn snapshots_reproduce_the_repos() {
+        let dir = scratch("snapshot-reproduce");
+        let snapshot = Snapshot::compile(dir.join("repo.yaml"), dir.join("manifests")).unwrap();
+        let path = dir.join("repo.snap");
+        snapshot.write(&path).unwrap();
+        let loaded = Snapshot::read(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        let expected: PackageRepo = format::load(dir.join("repo.yaml")).unwrap();
+        assert_eq!(loaded.package_repo().unwrap(), expected);
+
+        let arena = ManifestArena::new();
+        let manifests = loaded.manifest_repo(&arena).unwrap();
+        let expected_arena = ManifestArena::new();
+        let expected = ManifestRepo::from_disk(dir.join("manifests"), &expected_arena).unwrap();
+        assert_eq!(manifests.len(), expected.len());
+        for key in expected.keys() {
+            assert_eq!(manifests.get_package(key), expected.get_package(key));
+        }
+    }
+
+    #[test]
+    fn snapshots_go_stale_when_sources_change() {
+        let dir = scratch("snapshot-stale");
+        let path = dir.join("repo.snap");
+        let snapshot = Snapshot::load_or_compile(&path, dir.join("repo.yaml"), dir.join("manifests")).unwrap();
+        assert!(snapshot.is_fresh().unwrap());
+
+        // rewriting a file with the same contents leaves the snapshot fresh
+        let abc = dir.join("manifests").join("abc-0.1.0.yaml");
+        let contents = fs::read_to_string(&abc).unwrap();
+        fs::write(&abc, &contents).unwrap();
+        assert!(snapshot.is_fresh().unwrap());
+
+        fs::write(&abc, contents.replace("{}", "{ bar: '0.1.0' }")).unwrap();
+        assert!(!snapshot.is_fresh().unwrap());
+        let snapshot = Snapshot::load_or_compile(&path, dir.join("repo.yaml"), dir.join("manifests")).unwrap();
+        assert!(snapshot.is_fresh().unwrap());
+        let arena = ManifestArena::new();
+        let manifests = snapshot.manifest_repo(&arena).unwrap();
+        assert!(manifests.get_package(&Package::from_str("abc-0.1.0").unwrap()).unwrap().depends_on("bar"));
+
+        fs::write(dir.join("manifests").join("abc-0.2.0.yaml"), "name: abc-0.2.0\ndependencies: {}\n").unwrap();
+        assert!(!snapshot.is_fresh().unwrap());
+    }
+
+    #[test]
+    fn corrupt_snapshots_are_rejected_and_recompiled() {
+        let dir = scratch("snapshot-corrupt");
+        let path = dir.join("repo.snap");
+        let compile = || Snapshot::compile(dir.join("repo.yaml"), dir.join("manifests")).unwrap();
+
+        let mut snapshot = compile();
+        snapshot.packages[0].0 = snapshot.names.len() as NameIndex;
+        snapshot.write(&path).unwrap();
+        assert!(matches!(Snapshot::read(&path), Err(VersionitisError::InvalidSnapshot(_))));
+        assert!(snapshot.package_repo().is_err());
+
+        let mut snapshot = compile();
+        let (name, _) = snapshot.manifests.iter().flat_map(|m| &m.dependencies).flatten().next().unwrap();
+        let inverted = (*name, CompactInterval::HalfOpen(vec![2, 0, 0], vec![1, 0, 0]));
+        snapshot.manifests[0].conflicts.push(inverted);
+        snapshot.write(&path).unwrap();
+        assert!(matches!(Snapshot::read(&path), Err(VersionitisError::InvalidSnapshot(_))));
+        assert!(matches!(snapshot.manifest_repo(&ManifestArena::new()), Err(VersionitisError::InvalidSnapshot(_))));
+
+        let loaded = Snapshot::load_or_compile(&path, dir.join("repo.yaml"), dir.join("manifests")).unwrap();
+        assert_eq!(loaded, compile());
+        assert_eq!(Snapshot::read(&path).unwrap(), loaded);
+    }
+
+    #[test]
+    fn rejects_files_which_are_not_snapshots() {
+        let dir = scratch("snapshot-reject");
+        let result = Snapshot::read(dir.join("repo.yaml"));
+        assert!(matches!(result, Err(VersionitisError::InvalidSnapshot(_))));
+    }
+}
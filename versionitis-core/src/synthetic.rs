@@ -0,0 +1,216 @@
+//! synthetic.rs
+//!
+//! Generate synthetic repositories of any size, for benchmarks and tests.
+//!
+use crate::{
+    errors::VersionitisError,
+    format,
+    manifest::Manifest,
+    package::Package,
+    package_repo::PackageRepo,
+    version_number::VersionNumber,
+    version_number_interval::VersionNumberInterval,
+};
+use std::fs;
+use std::path::Path;
+
+/// The name of the PackageRepo file written by SyntheticRepo::write
+pub const PACKAGE_REPO_FILE: &str = "repo.yaml";
+
+/// The name of the manifest directory written by SyntheticRepo::write
+pub const MANIFEST_DIR: &str = "manifests";
+
+/// Configuration for a synthetic repository of `packages` packages, each with
+/// `versions` versions. Package `i` may depend only upon packages `0..i`, so
+/// the dependency graph is acyclic. Every dependency interval admits the
+/// latest version of its dependency, so every manifest resolves.
+///
+/// Generation is deterministic for a given configuration and seed.
+///
+/// # Example
+///
+/// ```
+/// use versionitis::synthetic::SyntheticRepo;
+/// let (repo, manifests) = SyntheticRepo::new(20, 3).density(2.0).seed(7).generate();
+/// assert_eq!(manifests.len(), 60);
+/// assert_eq!(repo.packages.len(), 20);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticRepo {
+    packages: usize,
+    versions: usize,
+    density: f64,
+    seed: u64,
+}
+
+impl SyntheticRepo {
+    /// New up a configuration with the default density of 3 and seed of 1
+    pub fn new(packages: usize, versions: usize) -> Self {
+        Self {
+            packages,
+            versions,
+            density: 3.0,
+            seed: 1,
+        }
+    }
+
+    /// Set the mean number of dependencies per manifest. Packages early in the
+    /// graph have fewer packages to depend upon, and so fewer dependencies.
+    pub fn density(mut self, density: f64) -> Self {
+        self.density = density.max(0.0);
+        self
+    }
+
+    /// Set the seed of the random number generator
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The name of the package with the supplied index
+    pub fn package_name(index: usize) -> String {
+        format!("pkg{:05}", index)
+    }
+
+    /// The version number of the supplied version index
+    pub fn version_number(index: usize) -> VersionNumber {
+        VersionNumber::new(vec![1 + (index / 10) as u16, (index % 10) as u16, 0])
+    }
+
+    /// Generate the PackageRepo and manifests, with manifests sorted by package
+    pub fn generate(&self) -> (PackageRepo, Vec<Manifest>) {
+        let mut rng = XorShift::new(self.seed);
+        let mut repo = PackageRepo::new();
+        let mut manifests = Vec::with_capacity(self.packages * self.versions);
+        for index in 0..self.packages {
+            let name = Self::package_name(index);
+            for version in 0..self.versions {
+                let package = Package::new(name.as_str(), Self::version_number(version));
+                repo.add_package(package).expect("versions are generated in order");
+                let mut manifest = Manifest::new(package);
+                for dependency in self.dependencies(index, &mut rng) {
+                    let interval = self.interval(&mut rng);
+                    manifest
+                        .add_dependency(Self::package_name(dependency), interval)
+                        .expect("dependencies are distinct");
+                }
+                manifests.push(manifest);
+            }
+        }
+        (repo, manifests)
+    }
+
+    /// Write the repository to a directory, as yaml manifests in the
+    /// `manifests` subdirectory, and a PackageRepo in `repo.yaml`.
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> Result<(), VersionitisError> {
+        let dir = dir.as_ref();
+        let manifest_dir = dir.join(MANIFEST_DIR);
+        fs::create_dir_all(&manifest_dir)?;
+        let (repo, manifests) = self.generate();
+        format::save(dir.join(PACKAGE_REPO_FILE), &repo)?;
+        for manifest in manifests {
+            format::save(manifest_dir.join(format!("{}.yaml", manifest.package())), &manifest)?;
+        }
+        Ok(())
+    }
+
+    // choose the distinct, lower indexed packages which a package depends upon
+    fn dependencies(&self, index: usize, rng: &mut XorShift) -> Vec<usize> {
+        if index == 0 || self.versions == 0 {
+            return Vec::new();
+        }
+        // the count is uniform over 0..=2 * density, so that its mean is density
+        let count = rng.below((2.0 * self.density) as u64 + 1) as usize;
+        let mut dependencies = (0..count.min(index))
+            .map(|_| rng.below(index as u64) as usize)
+            .collect::<Vec<_>>();
+        dependencies.sort();
+        dependencies.dedup();
+        dependencies
+    }
+
+    // choose an interval which admits the latest version of a dependency
+    fn interval(&self, rng: &mut XorShift) -> VersionNumberInterval {
+        let latest = Self::version_number(self.versions - 1);
+        let lower = Self::version_number(rng.below(self.versions as u64) as usize);
+        let spec = match rng.below(4) {
+            0 => latest.to_string(),
+            1 => format!("{}<={}", lower, latest),
+            _ => format!("{}<{}.0.0", lower, latest.digits()[0] + 1),
+        };
+        VersionNumberInterval::from_str(&spec).expect("generated intervals parse")
+    }
+}
+
+// A xorshift64* generator. Statistical quality is unimportant here; it only
+// needs to be fast and reproducible without pulling in a dependency.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // scramble the seed with a splitmix64 step, so that nearby seeds give
+        // unrelated sequences. The state must never be zero.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        XorShift((z ^ (z >> 31)).max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // a value in 0..bound, or 0 for a bound of 0
+    fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next() % bound
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consistency,
+        manifest::Phase,
+        manifest_repo::{ManifestArena, ManifestRepo},
+        test_util::ScratchDir,
+    };
+
+    #[test]
+    fn generation_is_deterministic() {
+        let config = SyntheticRepo::new(30, 4).density(4.0).seed(42);
+        assert_eq!(config.generate(), config.generate());
+        assert_ne!(config.generate().1, config.clone().seed(43).generate().1);
+    }
+
+    #[test]
+    fn generated_repos_are_consistent_and_resolve() {
+        let (repo, manifests) = SyntheticRepo::new(50, 5).density(3.0).generate();
+        let arena = ManifestArena::new();
+        let mut manifest_repo = ManifestRepo::new(&arena);
+        for manifest in manifests {
+            manifest_repo.add(manifest);
+        }
+        assert!(consistency::check(&repo, &manifest_repo).is_ok());
+        let top = Package::new(SyntheticRepo::package_name(49), SyntheticRepo::version_number(4));
+        assert!(manifest_repo.closure(&top, Phase::Run).is_ok());
+    }
+
+    #[test]
+    fn written_repos_load_from_disk() {
+        let dir = ScratchDir::new("synthetic");
+        SyntheticRepo::new(10, 3).write(&dir).unwrap();
+        let repo: PackageRepo = format::load(dir.join(PACKAGE_REPO_FILE)).unwrap();
+        assert_eq!(repo.packages.len(), 10);
+        let arena = ManifestArena::new();
+        let manifests = ManifestRepo::from_disk(dir.join(MANIFEST_DIR), &arena).unwrap();
+        assert_eq!(manifests.len(), 30);
+    }
+}
@@ -0,0 +1,104 @@
+//! test_util.rs
+//!
+//! Helpers shared by the tests of several modules. Other crates in the
+//! workspace reach them through the test-util feature.
+//!
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// distinguishes the scratch directories of tests running concurrently
+static NEXT_SCRATCH: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty directory, unique to a test, which is removed when dropped. As
+/// it is removed when a failing assertion unwinds, tests need not clean up.
+///
+/// # Example
+///
+/// ```ignore
+/// let dir = ScratchDir::new("snapshot");
+/// fs::write(dir.join("repo.yaml"), "packages: {}\n").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    /// New up an empty scratch directory. The name merely makes the
+    /// directory easier to recognize.
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "versionitis-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT_SCRATCH.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ScratchDir(dir)
+    }
+
+    /// Retrieve the path of the directory
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Copy the contents of a directory, recursively, into a subdirectory of
+    /// the scratch directory. An empty `dest` copies into the scratch
+    /// directory itself.
+    pub fn copy_from<P: AsRef<Path>, Q: AsRef<Path>>(&self, source: P, dest: Q) {
+        copy_dir(source.as_ref(), &self.0.join(dest))
+    }
+}
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// copy the files of a directory, and those of its subdirectories
+fn copy_dir(source: &Path, dest: &Path) {
+    fs::create_dir_all(dest).unwrap();
+    for entry in fs::read_dir(source).unwrap() {
+        let path = entry.unwrap().path();
+        let target = dest.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &target);
+        } else {
+            fs::copy(&path, &target).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scratch_dirs_are_distinct_and_removed_on_drop() {
+        let first = ScratchDir::new("scratch");
+        let second = ScratchDir::new("scratch");
+        assert_ne!(first.path(), second.path());
+        let path = first.to_path_buf();
+        first.copy_from(Path::new(env!("CARGO_MANIFEST_DIR")).join("test_resources").join("manifest_inheritance"), "");
+        assert!(path.join("templates").join("base.yaml").is_file());
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.is_dir());
+    }
+}
@@ -5,7 +5,7 @@
 use crate::errors::VersionitisError;
 use crate::version_number_interval::VersionNumberInterval;
 use crate::interval::Range;
-use pest::Parser;
+use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
 
 // We create a private _VerNumIntervalParser and a public
@@ -25,6 +25,8 @@ pub struct VerNumIntervalParser;
 impl VerNumIntervalParser {
 
     /// Convert a str to a VersionNumberInterval, or a VersionitisError if not successful.
+    /// Malformed input, such as an empty str or an empty version component
+    /// (eg 1..2), is reported as an error; parse does not panic.
     ///
     /// # Example
     /// ```
@@ -42,85 +44,58 @@ impl VerNumIntervalParser {
 
             match idx_piece.as_rule() {
                 Rule::single => {
-                    //let mut name=None;
-                    let mut version=None;
-                    for single_piece in idx_piece.into_inner() {
-                        let single_span = single_piece.clone().into_span();
-                        match single_piece.as_rule() {
-
-                            Rule::version_a => {
-                                version = Some(single_span.as_str());
-                            }
-                            _ => {}
-                        }
-                    }
-                    // assemble
-                    // we can safely unwrap these here because parsing
-                    // was successful.
-                    let name = format!("{}", version.unwrap());
-                    return VersionNumberInterval::from_range(&Range::Single(&name))
+                    let (version, _) = versions(input, idx_piece)?;
+                    return VersionNumberInterval::from_range(&Range::Single(version))
                 }
 
                 Rule::half_open => {
-                    //let mut name = None;
-                    let mut version_a = None;
-                    let mut version_b = None;
-
-                    for single_piece in idx_piece.into_inner() {
-                        let single_span = single_piece.clone().into_span();
-                        match single_piece.as_rule() {
-
-                            Rule::version_a => {
-                                version_a = Some(single_span.as_str());
-                            }
-
-                            Rule::version_b => {
-                                version_b = Some(single_span.as_str());
-                            }
-
-                            _ => {}
-                        }
-                    }
-                    // assemble. Unwrapping here is safe
-                    let v1 = format!("{}", version_a.unwrap());
-                    let v2 = format!("{}", version_b.unwrap());
-                    return VersionNumberInterval::from_range(&Range::HalfOpen(&v1, &v2))
+                    let (version_a, version_b) = versions(input, idx_piece)?;
+                    return VersionNumberInterval::from_range(&Range::HalfOpen(version_a, required(input, version_b)?))
                 }
 
                 Rule::open => {
-                    //let mut name = None;
-                    let mut version_a = None;
-                    let mut version_b = None;
-
-                    for single_piece in idx_piece.into_inner() {
-                        let single_span = single_piece.clone().into_span();
-                        match single_piece.as_rule() {
-
-                            Rule::version_a => {
-                                version_a = Some(single_span.as_str());
-                            }
-
-                            Rule::version_b => {
-                                version_b = Some(single_span.as_str());
-                            }
-
-                            _ => {}
-                        }
-                    }
-                    // assemble. Unwrapping here is safe
-                    let v1 = format!("{}",  version_a.unwrap());
-                    let v2 = format!("{}", version_b.unwrap());
-
-                    return VersionNumberInterval::from_range(&Range::Open(&v1, &v2))
+                    let (version_a, version_b) = versions(input, idx_piece)?;
+                    return VersionNumberInterval::from_range(&Range::Open(version_a, required(input, version_b)?))
                 }
 
-                _ => unreachable!()
+                Rule::empty => {
+                    return Ok(VersionNumberInterval::empty())
+                }
+
+                // the grammar accepts 1.2.3^, but it has no meaning yet
+                Rule::half_open2 => {
+                    return Err(VersionitisError::ParseError(
+                        format!("unable to parse: '{}' error: '^' intervals are not supported", input)
+                    ))
+                }
+
+                _ => {}
 
             };
         }
 
-        Err(VersionitisError::ParseError("NotImplemented".to_string()))
+        Err(VersionitisError::ParseError(format!("unable to parse: '{}'", input)))
+    }
+}
+
+// Retrieve the version_a and version_b spans of a parsed interval. Parsing
+// succeeded, so version_a should be present, but a missing span is reported
+// as an error rather than trusted, as the input may come from anywhere.
+fn versions<'a>(input: &str, pair: Pair<'a, Rule>) -> Result<(&'a str, Option<&'a str>), VersionitisError> {
+    let mut version_a = None;
+    let mut version_b = None;
+    for piece in pair.into_inner() {
+        match piece.as_rule() {
+            Rule::version_a => version_a = Some(piece.as_str()),
+            Rule::version_b => version_b = Some(piece.as_str()),
+            _ => {}
+        }
     }
+    Ok((required(input, version_a)?, version_b))
+}
+
+fn required<'a>(input: &str, version: Option<&'a str>) -> Result<&'a str, VersionitisError> {
+    version.ok_or_else(|| VersionitisError::ParseError(format!("unable to parse: '{}' error: 'missing version'", input)))
 }
 
 
@@ -165,4 +140,27 @@ mod test {
         assert_eq!(test, result);
     }
 
+    #[test]
+    fn can_parse_empty_interval() {
+        let test = VerNumIntervalParser::parse(" empty ");
+        assert_eq!(test, Ok(PI::empty()));
+    }
+
+    #[test]
+    fn rejects_inverted_intervals() {
+        let test = VerNumIntervalParser::parse("2.0.0<1.0.0");
+        assert_eq!(
+            test,
+            Err(VersionitisError::InvalidInterval("2.0.0<1.0.0 contains no values".to_string()))
+        );
+        assert!(VerNumIntervalParser::parse("2.0.0<=1.0.0").is_err());
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        for input in &["", " ", "1..2", ".1", "1.", "1.2.3^", "1.2.3<", "<=2.0", "99999", "1.2.3<2.0.0<3.0.0"] {
+            assert!(VerNumIntervalParser::parse(input).is_err(), "input: {:?}", input);
+        }
+    }
+
 }
\ No newline at end of file
@@ -1,25 +1,84 @@
 use crate::errors::VersionitisError;
+use crate::intern::{self, InternedVersion};
+use serde::{Deserializer, Serializer};
 use serde_derive::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt;
-use std::string::ToString;
+use std::hash::{Hash, Hasher};
 
 /// VersionNumber implements Versionable trait. A VersionNumber may be comprised of one or more u16 digits
-#[derive(PartialEq, PartialOrd, Eq, Ord, Deserialize, Serialize, Hash, Clone)]
-pub struct VersionNumber {
-    value: Vec<u16>,
-    name: String,
+///
+/// A VersionNumber is a Copy handle to interned digits, so each distinct
+/// version is stored once, however many packages share it.
+#[derive(Clone, Copy)]
+pub struct VersionNumber(&'static InternedVersion);
+
+impl PartialEq for VersionNumber {
+    fn eq(&self, other: &Self) -> bool {
+        // interned, so equal digits share an address
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for VersionNumber {}
+
+impl PartialOrd for VersionNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VersionNumber {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.value.cmp(&other.0.value)
+    }
+}
+
+impl Hash for VersionNumber {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.value.hash(state)
+    }
+}
+
+// The serialized form of a VersionNumber
+#[derive(Deserialize, Serialize)]
+#[serde(rename = "VersionNumber")]
+struct VersionNumberData<'a> {
+    value: Cow<'a, [u16]>,
+    name: Cow<'a, str>,
+}
+
+impl serde::Serialize for VersionNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = VersionNumberData {
+            value: Cow::Borrowed(&self.0.value),
+            name: Cow::Borrowed(&self.0.name),
+        };
+        serde::Serialize::serialize(&data, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for VersionNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data: VersionNumberData = serde::Deserialize::deserialize(deserializer)?;
+        if data.value.is_empty() {
+            return Err(serde::de::Error::custom("a version number requires at least one digit"));
+        }
+        Ok(VersionNumber::new(data.value.into_owned()))
+    }
 }
 
 impl fmt::Debug for VersionNumber {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.name)
+        write!(f, "{}", self.name())
     }
 }
 
 //self.0.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(".")
 impl fmt::Display for VersionNumber {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let r = write!(f, "{}", self.name);
+        let r = write!(f, "{}", self.name());
         r
     }
 }
@@ -27,16 +86,20 @@ impl fmt::Display for VersionNumber {
 impl VersionNumber {
     /// Construct a VersionNumber from a vector of u16
     pub fn new(value: Vec<u16>) -> Self {
-        let name = value.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(".");
-        VersionNumber{value, name}
+        VersionNumber(intern::version(&value))
     }
 
     pub fn value(&self) -> Vec<u16> {
-        self.value.clone()
+        self.0.value.clone()
+    }
+
+    /// Retrieve the digits of the version number without copying them
+    pub fn digits(&self) -> &'static [u16] {
+        &self.0.value
     }
 
-    pub fn name(&self) -> &str {
-        self.name.as_str()
+    pub fn name(&self) -> &'static str {
+        self.0.name.as_str()
     }
 
     /// construct a VersionNumber with 3 u16 values
@@ -159,6 +222,19 @@ mod tests {
         assert_eq!(result, package);
     }
 
+    #[test]
+    fn empty_components_are_an_error() {
+        for input in &["", ".", "1..2", ".1", "1.", "1.a", "65536"] {
+            assert!(VersionNumber::from_str(input).is_err(), "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn deserializing_a_version_without_digits_is_an_error() {
+        let result: serde_yaml::Result<VersionNumber> = serde_yaml::from_str("value: []\nname: ''\n");
+        assert!(result.is_err());
+    }
+
     #[test]
     fn can_generate_versionnumber_from_str() {
         let package = String::from("0.1.0.1");
@@ -40,6 +40,7 @@ impl VersionNumberInterval {
     /// Single(1.2.3) | 1.2.3
     /// HalfOpen(1.2.3, 2.0.0) | 1.2.3<2.0.0
     /// Open(1.2.3, 2.0.0) | 1.2.3<=2.0.0
+    /// Empty | empty
     pub fn to_range(&self) -> String {
         match *self {
             Interval::Single(ref v) => {
@@ -53,6 +54,8 @@ impl VersionNumberInterval {
             Interval::Open { ref start, ref end } => {
                 format!("{}<={}",start.to_string(), end.to_string())
             }
+
+            Interval::Empty => "empty".to_string(),
         }
     }
 
@@ -64,6 +67,10 @@ impl VersionNumberInterval {
     /// let package_interval = PackageInterval::from_range(&Range::Open("foo-0.1.0", "foo-1.0.0"))?;
     /// ```
     ///
+    /// Inverted (start > end) and empty (start == end for HalfOpen) ranges are
+    /// rejected with an InvalidInterval error. Use `Interval::empty()` if an
+    /// interval which contains nothing is really what you mean.
+    ///
     /// One may wish to make this more ergonomic though:
     ///
     /// ```ignore
@@ -77,15 +84,15 @@ impl VersionNumberInterval {
                 Ok(Interval::Single(VersionNumber::from_str(name)?))
             },
 
-            Range::HalfOpen(ref p1, ref p2) => Ok(Interval::HalfOpen {
-                start: VersionNumber::from_str(p1)?,
-                end: VersionNumber::from_str(p2)?,
-            }),
+            Range::HalfOpen(ref p1, ref p2) => Interval::half_open(
+                VersionNumber::from_str(p1)?,
+                VersionNumber::from_str(p2)?,
+            ),
 
-            Range::Open(ref p1, ref p2) => Ok(Interval::Open {
-                start: VersionNumber::from_str(p1)?,
-                end: VersionNumber::from_str(p2)?,
-            }),
+            Range::Open(ref p1, ref p2) => Interval::open(
+                VersionNumber::from_str(p1)?,
+                VersionNumber::from_str(p2)?,
+            ),
         }
     }
 }
@@ -114,11 +121,8 @@ impl<'de> Visitor<'de> for VersionNumberIntervalVisitor {
     where
         E: de::Error,
     {
-        match VerNumIntervalParser::parse(value) {
-            Ok(v) => Ok(v),
-            //TODO: look into returning an error instead of panicing.
-            Err(e) => panic!("unable to deserialize: {}", e),
-        }
+        VerNumIntervalParser::parse(value)
+            .map_err(|e| de::Error::custom(format!("unable to deserialize: {}", e)))
     }
 }
 
@@ -185,6 +189,39 @@ mod test {
         assert_eq!(result, expect);
     }
 
+    #[test]
+    fn can_serialize_and_deserialize_an_empty_interval() {
+        let interval = VersionNumberInterval::empty();
+        let result = serde_yaml::to_string(&interval).unwrap();
+        let expect: VersionNumberInterval = serde_yaml::from_str(&result).unwrap();
+        assert_eq!(interval, expect);
+    }
+
+    #[test]
+    fn from_range_rejects_inverted_intervals() {
+        let result = VersionNumberInterval::from_range(&Range::HalfOpen("2.0.0", "1.0.0"));
+        assert_eq!(
+            result,
+            Err(VersionitisError::InvalidInterval("2.0.0<1.0.0 contains no values".to_string()))
+        );
+        let result = VersionNumberInterval::from_range(&Range::Open("2.0.0", "1.0.0"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn from_range_rejects_empty_half_open_intervals() {
+        let result = VersionNumberInterval::from_range(&Range::HalfOpen("1.0.0", "1.0.0"));
+        assert!(result.is_err());
+        let result = VersionNumberInterval::from_range(&Range::Open("1.0.0", "1.0.0"));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn deserializing_an_inverted_interval_is_an_error() {
+        let result: serde_yaml::Result<VersionNumberInterval> = serde_yaml::from_str("---\n2.0.0<1.0.0");
+        assert!(result.is_err());
+    }
+
     #[test]
     fn can_convert_a_single_to_range() {
         let pi = VersionNumberInterval::from_range(&Range::Single("1.2.3")).unwrap();
@@ -0,0 +1,298 @@
+//! watch.rs
+//!
+//! Keep a manifest directory, and optionally a PackageRepo file, in sync
+//! with disk, reporting what changed as typed events.
+//!
+//! Every package name, version and variant read while watching is interned
+//! for the life of the process (see `intern`), and is not released when its
+//! manifest is edited or removed. Memory therefore grows with the number of
+//! distinct names and versions ever seen, not with the size of the studio.
+//! Rereading unchanged files costs nothing, but a long-running watcher over
+//! a directory with unbounded churn, such as one stamping a new version on
+//! every build, should be restarted from time to time. `intern::stats`
+//! reports how many values have been interned.
+//!
+use crate::{
+    errors::VersionitisError,
+    format,
+    manifest_cache::ManifestCache,
+    package::Package,
+    package_repo::PackageRepo,
+    snapshot::SourceFile,
+};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A change observed by a RepoWatcher
+#[derive(Debug, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// a manifest was added for the package
+    ManifestAdded(Package),
+    /// the effective manifest for the package changed
+    ManifestUpdated(Package),
+    /// the manifest for the package was removed
+    ManifestRemoved(Package),
+    /// the package was added to the PackageRepo
+    PackageAdded(Package),
+    /// the package was removed from the PackageRepo
+    PackageRemoved(Package),
+    /// a file failed to load. The previous version, if any, is kept.
+    Error(PathBuf, VersionitisError),
+}
+
+// The PackageRepo file being watched, along with its last good contents
+#[derive(Debug)]
+struct WatchedPackageRepo {
+    path: PathBuf,
+    source: SourceFile,
+    repo: PackageRepo,
+}
+
+/// The RepoWatcher keeps a ManifestCache, and optionally a PackageRepo, in sync
+/// with disk by polling. Polling works on network filesystems, where change
+/// notifications are unreliable, and each poll only reads files whose size or
+/// modification time changed.
+///
+/// Edits which fail to load are reported as WatchEvent::Error, and the last
+/// good version of the file is kept.
+///
+/// # Example
+///
+/// ```ignore
+/// let watcher = RepoWatcher::new("manifests")?.with_package_repo("repo.yaml")?;
+/// let watch = watcher.spawn(Duration::from_secs(1));
+/// for event in watch.events() {
+///     println!("{:?}", event);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RepoWatcher {
+    manifests: ManifestCache,
+    package_repo: Option<WatchedPackageRepo>,
+}
+
+impl RepoWatcher {
+    /// New up a watcher for a manifest directory, loading it. Unlike later
+    /// polls, the initial load fails if any manifest fails to load.
+    pub fn new<P: Into<PathBuf>>(manifest_dir: P) -> Result<Self, VersionitisError> {
+        Ok(Self {
+            manifests: ManifestCache::load(manifest_dir)?,
+            package_repo: None,
+        })
+    }
+
+    /// Watch a PackageRepo file as well, loading it in any supported format
+    pub fn with_package_repo<P: Into<PathBuf>>(mut self, path: P) -> Result<Self, VersionitisError> {
+        let path = path.into();
+        let (source, contents) = SourceFile::read(path.clone())?;
+        let repo = format::Format::from_path(&path)?.parse(&contents)?;
+        self.package_repo = Some(WatchedPackageRepo { path, source, repo });
+        Ok(self)
+    }
+
+    /// Retrieve the watched manifests
+    pub fn manifests(&self) -> &ManifestCache {
+        &self.manifests
+    }
+
+    /// Retrieve the watched PackageRepo, if any
+    pub fn package_repo(&self) -> Option<&PackageRepo> {
+        self.package_repo.as_ref().map(|watched| &watched.repo)
+    }
+
+    /// Check for changes on disk, returning the resulting events. Manifest
+    /// events are sorted by package, followed by PackageRepo events and errors.
+    pub fn poll(&mut self) -> Vec<WatchEvent> {
+        let mut events = Vec::new();
+        match self.manifests.refresh() {
+            Ok((diff, errors)) => {
+                events.extend(diff.added.into_iter().map(|m| WatchEvent::ManifestAdded(*m.package())));
+                events.extend(diff.modified.into_iter().map(|(_, m)| WatchEvent::ManifestUpdated(*m.package())));
+                events.extend(diff.removed.into_iter().map(|m| WatchEvent::ManifestRemoved(*m.package())));
+                events.extend(errors.into_iter().map(|(path, error)| WatchEvent::Error(path, error)));
+            }
+            Err(error) => events.push(WatchEvent::Error(self.manifests.dir().to_path_buf(), error)),
+        }
+        if let Some(watched) = self.package_repo.as_mut() {
+            watched.poll(&mut events);
+        }
+        events
+    }
+
+    /// Poll on a background thread at the supplied interval, sending events
+    /// to the returned Watch. Stopping the Watch returns the watcher.
+    pub fn spawn(mut self, interval: Duration) -> Watch {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let handle = thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                for event in self.poll() {
+                    if sender.send(event).is_err() {
+                        return self;
+                    }
+                }
+                thread::sleep(interval);
+            }
+            self
+        });
+        Watch { events: receiver, stop, handle }
+    }
+}
+
+impl WatchedPackageRepo {
+    // reload the PackageRepo if it changed, keeping the old one if the new
+    // one fails to load
+    fn poll(&mut self, events: &mut Vec<WatchEvent>) {
+        if self.source.metadata_matches() {
+            return;
+        }
+        let (source, contents) = match SourceFile::read(self.path.clone()) {
+            Ok(read) => read,
+            Err(error) => {
+                events.push(WatchEvent::Error(self.path.clone(), error));
+                return;
+            }
+        };
+        let repo = if source.hash() == self.source.hash() {
+            None
+        } else {
+            match format::Format::from_path(&self.path).and_then(|format| format.parse::<PackageRepo>(&contents)) {
+                Ok(repo) => Some(repo),
+                Err(error) => {
+                    // record the state of the bad file so that it is not
+                    // reported again until it changes
+                    self.source = source;
+                    events.push(WatchEvent::Error(self.path.clone(), error));
+                    return;
+                }
+            }
+        };
+        self.source = source;
+        if let Some(repo) = repo {
+            let old = packages(&self.repo);
+            let new = packages(&repo);
+            let mut added = new.difference(&old).cloned().collect::<Vec<_>>();
+            let mut removed = old.difference(&new).cloned().collect::<Vec<_>>();
+            added.sort();
+            removed.sort();
+            events.extend(added.into_iter().map(WatchEvent::PackageAdded));
+            events.extend(removed.into_iter().map(WatchEvent::PackageRemoved));
+            self.repo = repo;
+        }
+    }
+}
+
+// collect the packages in a PackageRepo
+fn packages(repo: &PackageRepo) -> HashSet<Package> {
+    repo.packages.values().flatten().cloned().collect()
+}
+
+/// A RepoWatcher polling on a background thread
+pub struct Watch {
+    events: Receiver<WatchEvent>,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<RepoWatcher>,
+}
+
+impl Watch {
+    /// Retrieve the receiver of events
+    pub fn events(&self) -> &Receiver<WatchEvent> {
+        &self.events
+    }
+
+    /// Wait up to `timeout` for the next event
+    pub fn next_event(&self, timeout: Duration) -> Option<WatchEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    /// Stop polling, returning the watcher once its current poll completes
+    pub fn stop(self) -> RepoWatcher {
+        self.stop.store(true, Ordering::SeqCst);
+        self.handle.join().expect("watch thread panicked")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::ScratchDir, traits::TrackPackages};
+    use std::fs;
+
+    fn scratch(name: &str) -> ScratchDir {
+        let dir = ScratchDir::new(name);
+        fs::create_dir_all(dir.join("manifests")).unwrap();
+        fs::write(dir.join("manifests").join("foo-1.0.0.yaml"), "name: foo-1.0.0\ndependencies: {}\n").unwrap();
+        let mut repo = PackageRepo::new();
+        repo.add_version("foo", "1.0.0").unwrap();
+        format::save(dir.join("repo.yaml"), &repo).unwrap();
+        dir
+    }
+
+    fn package(spec: &str) -> Package {
+        Package::from_str(spec).unwrap()
+    }
+
+    #[test]
+    fn reports_manifest_changes() {
+        let dir = scratch("watch-manifests");
+        let mut watcher = RepoWatcher::new(dir.join("manifests")).unwrap();
+        assert!(watcher.poll().is_empty());
+
+        fs::write(dir.join("manifests").join("bar-1.0.0.yaml"), "name: bar-1.0.0\ndependencies: {}\n").unwrap();
+        fs::write(dir.join("manifests").join("foo-1.0.0.yaml"), "name: foo-1.0.0\ndependencies:\n  bar: '1.0.0'\n").unwrap();
+        assert_eq!(
+            watcher.poll(),
+            vec![WatchEvent::ManifestAdded(package("bar-1.0.0")), WatchEvent::ManifestUpdated(package("foo-1.0.0"))]
+        );
+
+        fs::remove_file(dir.join("manifests").join("bar-1.0.0.yaml")).unwrap();
+        assert_eq!(watcher.poll(), vec![WatchEvent::ManifestRemoved(package("bar-1.0.0"))]);
+    }
+
+    #[test]
+    fn malformed_edits_keep_the_old_version() {
+        let dir = scratch("watch-malformed");
+        let mut watcher = RepoWatcher::new(dir.join("manifests")).unwrap().with_package_repo(dir.join("repo.yaml")).unwrap();
+        let foo = dir.join("manifests").join("foo-1.0.0.yaml");
+        fs::write(&foo, "name: foo-1.0.0\ndependencies: [").unwrap();
+        fs::write(dir.join("repo.yaml"), "packages: [").unwrap();
+
+        let events = watcher.poll();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], WatchEvent::Error(path, _) if path == &foo));
+        assert!(matches!(&events[1], WatchEvent::Error(path, _) if path == &dir.join("repo.yaml")));
+        assert!(watcher.manifests().get(&package("foo-1.0.0")).is_some());
+        assert!(watcher.package_repo().unwrap().get("foo").is_ok());
+        // bad files are not reported again until they change
+        assert!(watcher.poll().is_empty());
+
+        fs::write(&foo, "name: foo-1.0.0\ndependencies:\n  bar: '1.0.0'\n").unwrap();
+        let mut repo = PackageRepo::new();
+        repo.add_version("foo", "1.0.0").unwrap();
+        repo.add_version("bar", "1.0.0").unwrap();
+        format::save(dir.join("repo.yaml"), &repo).unwrap();
+        assert_eq!(
+            watcher.poll(),
+            vec![WatchEvent::ManifestUpdated(package("foo-1.0.0")), WatchEvent::PackageAdded(package("bar-1.0.0"))]
+        );
+    }
+
+    #[test]
+    fn can_watch_in_the_background() {
+        let dir = scratch("watch-background");
+        let watch = RepoWatcher::new(dir.join("manifests")).unwrap().spawn(Duration::from_millis(10));
+        fs::write(dir.join("manifests").join("bar-1.0.0.yaml"), "name: bar-1.0.0\ndependencies: {}\n").unwrap();
+        assert_eq!(
+            watch.next_event(Duration::from_secs(5)),
+            Some(WatchEvent::ManifestAdded(package("bar-1.0.0")))
+        );
+        let watcher = watch.stop();
+        assert_eq!(watcher.manifests().len(), 2);
+    }
+}
@@ -0,0 +1,415 @@
+//! commands.rs
+//!
+//! The implementation of each versiony subcommand. Commands write their
+//! output to the supplied writer, and report whether they found problems,
+//! so that main can choose an exit code.
+//!
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use versionitis::{
+    consistency,
+    errors::VersionitisError,
+    format::{self, Format},
+    inheritance::ManifestResolver,
+    lint::{self, LintConfig, LintReport},
+    manifest::Phase,
+    manifest_repo::{ManifestArena, ManifestRepo},
+    package::Package,
+    traits::TrackPackages,
+    PackageRepo,
+};
+
+/// The outcome of a command which ran to completion
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Outcome {
+    /// the command succeeded, and found nothing wrong
+    Clean,
+    /// the command ran, but found problems, such as lint errors, invalid
+    /// manifests or dependencies which cannot be resolved
+    Problems,
+}
+
+// io errors writing output are reported like any other io error
+fn io(err: std::io::Error) -> VersionitisError {
+    VersionitisError::IoError(err.to_string())
+}
+
+// report a missing input by its path, rather than as a bare io error
+fn extant(path: &Path) -> Result<&Path, VersionitisError> {
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(VersionitisError::NonExtantFileError(format!("path:'{}' doesn't exist", path.display())))
+    }
+}
+
+/// List the package names in a PackageRepo file, or the versions of a
+/// single package, one per line.
+pub fn repo_list(out: &mut dyn Write, path: &Path, package: Option<&str>) -> Result<Outcome, VersionitisError> {
+    let repo: PackageRepo = format::load(extant(path)?)?;
+    match package {
+        Some(name) => {
+            for package in repo.get(name)? {
+                writeln!(out, "{}", package).map_err(io)?;
+            }
+        }
+        None => {
+            let mut names = repo.packages.keys().map(|name| name.as_str()).collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                writeln!(out, "{}", name).map_err(io)?;
+            }
+        }
+    }
+    Ok(Outcome::Clean)
+}
+
+/// Add package versions (eg foo-1.2.0) to a PackageRepo file, creating the
+/// file if it does not exist. Each version must be greater than the last
+/// version of its package. Nothing is written unless every version is added.
+pub fn repo_add(out: &mut dyn Write, path: &Path, packages: &[String]) -> Result<Outcome, VersionitisError> {
+    let mut repo = if path.exists() { format::load(path)? } else { PackageRepo::new() };
+    for spec in packages {
+        repo.add_package(Package::from_str(spec)?)?;
+    }
+    format::save(path, &repo)?;
+    for spec in packages {
+        writeln!(out, "added {}", spec).map_err(io)?;
+    }
+    Ok(Outcome::Clean)
+}
+
+/// Print a manifest file in the supplied format. Given a manifest directory
+/// and a package instead, print the package's effective manifest, with its
+/// `extends` chain flattened.
+pub fn manifest_show(
+    out: &mut dyn Write,
+    path: &Path,
+    package: Option<&str>,
+    format: Format,
+) -> Result<Outcome, VersionitisError> {
+    let manifest = match package {
+        Some(package) => ManifestResolver::from_disk(path)?.effective(&Package::from_str(package)?)?,
+        None => format::load_manifest(extant(path)?)?,
+    };
+    write!(out, "{}", format.render(&manifest)?).map_err(io)?;
+    Ok(Outcome::Clean)
+}
+
+/// Check manifest files and directories. Each file must load, each
+/// directory's `extends` chains must resolve, and, given a PackageRepo file,
+/// each directory must be consistent with it. Every problem is reported,
+/// rather than stopping at the first. A path which does not exist is an error.
+pub fn manifest_check(
+    out: &mut dyn Write,
+    paths: &[PathBuf],
+    packages: Option<&Path>,
+) -> Result<Outcome, VersionitisError> {
+    let packages: Option<PackageRepo> = packages.map(|path| format::load(extant(path)?)).transpose()?;
+    let mut checked = 0;
+    let mut problems = 0;
+    for path in paths {
+        let files = if path.is_dir() { manifest_files(path)? } else { vec![extant(path)?.to_path_buf()] };
+        let mut loaded = true;
+        for file in files {
+            checked += 1;
+            if let Err(err) = format::load_manifest(&file) {
+                writeln!(out, "{}: {}", file.display(), err).map_err(io)?;
+                problems += 1;
+                loaded = false;
+            }
+        }
+        // resolving a directory only makes sense once each file loads
+        if !path.is_dir() || !loaded {
+            continue;
+        }
+        let arena = ManifestArena::new();
+        match ManifestRepo::from_disk(path, &arena) {
+            Err(err) => {
+                writeln!(out, "{}: {}", path.display(), err).map_err(io)?;
+                problems += 1;
+            }
+            Ok(manifests) => {
+                if let Some(ref packages) = packages {
+                    let report = consistency::check(packages, &manifests);
+                    for issue in report.issues() {
+                        writeln!(out, "{}: {}", path.display(), issue).map_err(io)?;
+                    }
+                    problems += report.len();
+                }
+            }
+        }
+    }
+    writeln!(out, "checked {} manifests: {} problems", checked, problems).map_err(io)?;
+    Ok(if problems == 0 { Outcome::Clean } else { Outcome::Problems })
+}
+
+// parse the root of a closure, which must have a manifest in the repo. A
+// missing root is a mistake in the arguments rather than a problem in the repo.
+fn root(repo: &ManifestRepo, package: &str) -> Result<Package, VersionitisError> {
+    let package = Package::from_str(package)?;
+    match repo.get_package(&package) {
+        Some(_) => Ok(package),
+        None => Err(VersionitisError::UnknownPackage(format!("no manifest for {}", package))),
+    }
+}
+
+/// Resolve the dependency closure of a package for a phase, printing the
+/// selected packages one per line. A closure which cannot be resolved is
+/// reported as a problem, while a package without a manifest is an error.
+pub fn resolve(out: &mut dyn Write, dir: &Path, package: &str, phase: Phase) -> Result<Outcome, VersionitisError> {
+    let arena = ManifestArena::new();
+    let repo = ManifestRepo::from_disk(dir, &arena)?;
+    let package = root(&repo, package)?;
+    match repo.closure(&package, phase) {
+        Ok(closure) => {
+            for manifest in closure {
+                writeln!(out, "{}", manifest.package()).map_err(io)?;
+            }
+            Ok(Outcome::Clean)
+        }
+        Err(err) => {
+            writeln!(out, "unable to resolve {}: {}", package, err).map_err(io)?;
+            Ok(Outcome::Problems)
+        }
+    }
+}
+
+/// Print the dependency graph of a manifest directory for a phase in
+/// graphviz dot format. Each dependency points at the highest version which
+/// satisfies it, or, for a dependency which nothing satisfies, at a red,
+/// dashed node bearing its name. Given a package, only its resolved closure
+/// is drawn, and a closure which cannot be resolved is reported as a problem,
+/// while a package without a manifest is an error.
+pub fn graph(out: &mut dyn Write, dir: &Path, package: Option<&str>, phase: Phase) -> Result<Outcome, VersionitisError> {
+    let arena = ManifestArena::new();
+    let repo = ManifestRepo::from_disk(dir, &arena)?;
+    let mut nodes = match package {
+        Some(package) => {
+            let package = root(&repo, package)?;
+            let mut nodes = match repo.closure(&package, phase) {
+                Ok(nodes) => nodes,
+                Err(err) => {
+                    writeln!(out, "unable to resolve {}: {}", package, err).map_err(io)?;
+                    return Ok(Outcome::Problems);
+                }
+            };
+            nodes.extend(repo.get_package(&package));
+            nodes
+        }
+        None => repo.keys().filter_map(|package| repo.get_package(package)).collect::<Vec<_>>(),
+    };
+    nodes.sort_by_key(|manifest| *manifest.package());
+    let drawn = nodes.iter().map(|manifest| *manifest.package()).collect::<BTreeSet<Package>>();
+
+    writeln!(out, "digraph dependencies {{").map_err(io)?;
+    for manifest in &nodes {
+        writeln!(out, "    \"{}\";", manifest.package()).map_err(io)?;
+        let mut dependencies = manifest.dependencies_for(phase).iter().collect::<Vec<_>>();
+        dependencies.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, interval) in dependencies {
+            let target = repo
+                .satisfying(name, interval)
+                .into_iter()
+                .map(|(_, satisfier)| *satisfier.package())
+                .find(|satisfier| drawn.contains(satisfier));
+            match target {
+                Some(target) => writeln!(
+                    out,
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                    manifest.package(),
+                    target,
+                    interval
+                ),
+                None => writeln!(
+                    out,
+                    "    \"{}\" -> \"{}\" [label=\"{}\", style=dashed, color=red];",
+                    manifest.package(),
+                    name,
+                    interval
+                ),
+            }
+            .map_err(io)?;
+        }
+    }
+    writeln!(out, "}}").map_err(io)?;
+    Ok(Outcome::Clean)
+}
+
+/// Lint manifest files and directories, printing the diagnostics as text or
+/// json. Directories are linted as effective manifests. Any diagnostic with a
+/// severity of deny is a problem.
+pub fn lint(out: &mut dyn Write, paths: &[PathBuf], config: &LintConfig, json: bool) -> Result<Outcome, VersionitisError> {
+    let mut report = LintReport::new();
+    for path in paths {
+        let manifests = if path.is_dir() {
+            ManifestResolver::from_disk(path)?.resolve_all()?
+        } else {
+            vec![format::load_manifest(extant(path)?)?]
+        };
+        for manifest in &manifests {
+            report.extend(lint::lint_manifest(manifest, config));
+        }
+    }
+    if json {
+        writeln!(out, "{}", report.to_json()?).map_err(io)?;
+    } else {
+        write!(out, "{}", report.to_text()).map_err(io)?;
+    }
+    Ok(if report.has_errors() { Outcome::Problems } else { Outcome::Clean })
+}
+
+// list the manifest files directly within a directory, sorted by path.
+// Templates, in the templates subdirectory, are not manifests in their own
+// right, so are only checked when the directory is resolved.
+fn manifest_files(dir: &Path) -> Result<Vec<PathBuf>, VersionitisError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && Format::from_path(&path).is_ok() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use versionitis::{lint::Severity, test_util::ScratchDir};
+
+    fn resources(name: &str) -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("..");
+        path.push("versionitis-core");
+        path.push("test_resources");
+        path.push(name);
+        path
+    }
+
+    fn run<F: FnOnce(&mut dyn Write) -> Result<Outcome, VersionitisError>>(command: F) -> (Outcome, String) {
+        let mut out = Vec::new();
+        let outcome = command(&mut out).unwrap();
+        (outcome, String::from_utf8(out).unwrap())
+    }
+
+    // a manifest directory in which foo-1.0.0 depends upon a missing package
+    fn unresolvable() -> ScratchDir {
+        let dir = ScratchDir::new("versiony-unresolvable");
+        fs::write(dir.join("foo-1.0.0.yaml"), "name: foo-1.0.0\ndependencies: {bar: '1.0.0'}\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn can_add_to_and_list_a_repo() {
+        let dir = ScratchDir::new("versiony-repo");
+        let path = dir.join("repo.yaml");
+        let specs = vec!["foo-0.1.0".to_string(), "foo-0.2.0".to_string(), "bar-1.0.0".to_string()];
+        run(|out| repo_add(out, &path, &specs));
+        assert_eq!(run(|out| repo_list(out, &path, None)).1, "bar\nfoo\n");
+        assert_eq!(run(|out| repo_list(out, &path, Some("foo"))).1, "foo-0.1.0\nfoo-0.2.0\n");
+
+        // versions must increase, and a failed add leaves the file untouched
+        let before = fs::read_to_string(&path).unwrap();
+        let specs = vec!["bla-1.0.0".to_string(), "foo-0.1.0".to_string()];
+        assert!(repo_add(&mut Vec::new(), &path, &specs).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn can_show_effective_manifests() {
+        let dir = resources("manifest_inheritance");
+        let (_, text) = run(|out| manifest_show(out, &dir, Some("foo-1.1.0"), Format::Yaml));
+        let manifest = Format::Yaml.parse_manifest(&text).unwrap();
+        assert_eq!(manifest.extends(), None);
+        assert_eq!(manifest.dependencies().get("bar").unwrap().to_string(), "0.2.0");
+        assert_eq!(manifest.dependencies().get("abc").unwrap().to_string(), "0.1.0");
+    }
+
+    #[test]
+    fn check_reports_every_bad_manifest() {
+        let (outcome, text) = run(|out| manifest_check(out, &[resources("manifest_repo")], None));
+        assert_eq!(outcome, Outcome::Clean);
+        assert_eq!(text, "checked 6 manifests: 0 problems\n");
+
+        let dir = ScratchDir::new("versiony-check");
+        fs::write(dir.join("bar-1.0.0.yaml"), "name: bar-1.0.0\ndependencies: {foo: '1..2'}\n").unwrap();
+        fs::write(dir.join("bla-1.0.0.yaml"), "name: bla-1.0.0\ndependencies: {foo: '2<1'}\n").unwrap();
+        fs::write(dir.join("foo-1.0.0.yaml"), "name: foo-1.0.0\ndependencies: {}\n").unwrap();
+        let (outcome, text) = run(|out| manifest_check(out, &[dir.to_path_buf()], None));
+        assert_eq!(outcome, Outcome::Problems);
+        assert!(text.contains("bar-1.0.0.yaml") && text.contains("bla-1.0.0.yaml"), "{}", text);
+        assert!(!text.contains("foo-1.0.0.yaml"), "{}", text);
+        assert!(text.ends_with("checked 3 manifests: 2 problems\n"), "{}", text);
+        assert!(matches!(
+            manifest_check(&mut Vec::new(), &[dir.join("nope.yaml")], None),
+            Err(VersionitisError::NonExtantFileError(_))
+        ));
+    }
+
+    #[test]
+    fn check_cross_checks_a_package_repo() {
+        let dir = ScratchDir::new("versiony-consistency");
+        let path = dir.join("repo.yaml");
+        let specs = vec!["foo-1.0.0".to_string()];
+        run(|out| repo_add(out, &path, &specs));
+        let (outcome, text) = run(|out| manifest_check(out, &[resources("manifest_repo")], Some(&path)));
+        assert_eq!(outcome, Outcome::Problems);
+        assert!(text.contains("abc-0.1.0: manifest has no released version"), "{}", text);
+    }
+
+    #[test]
+    fn can_resolve_a_closure() {
+        let dir = resources("manifest_repo");
+        let (outcome, text) = run(|out| resolve(out, &dir, "foo-1.0.0", Phase::Run));
+        assert_eq!(outcome, Outcome::Clean);
+        assert_eq!(text, "abc-0.1.0\nbar-0.1.0\nbla-0.3.0\n");
+        assert_eq!(
+            resolve(&mut Vec::new(), &dir, "nope-1.0.0", Phase::Run),
+            Err(VersionitisError::UnknownPackage("no manifest for nope-1.0.0".to_string()))
+        );
+        assert!(resolve(&mut Vec::new(), &dir, "nope", Phase::Run).is_err());
+
+        // a closure which exists but cannot be resolved is a problem
+        let dir = unresolvable();
+        let (outcome, text) = run(|out| resolve(out, &dir, "foo-1.0.0", Phase::Run));
+        assert_eq!(outcome, Outcome::Problems);
+        assert!(text.starts_with("unable to resolve foo-1.0.0"), "{}", text);
+    }
+
+    #[test]
+    fn can_graph_a_closure() {
+        let dir = resources("manifest_repo");
+        let (_, text) = run(|out| graph(out, &dir, Some("foo-1.0.0"), Phase::Run));
+        assert!(text.starts_with("digraph dependencies {\n"));
+        assert!(text.contains("    \"foo-1.0.0\" -> \"bla-0.3.0\" [label=\"0.2.0<=1.0.0\"];\n"), "{}", text);
+        assert!(!text.contains("foo-0.1.0"));
+        let (_, text) = run(|out| graph(out, &dir, None, Phase::Run));
+        assert!(text.contains("    \"foo-0.1.0\" -> \"bla-0.3.0\" [label=\"0.1.0<=1.0.0\"];\n"), "{}", text);
+        assert!(matches!(
+            graph(&mut Vec::new(), &dir, Some("nope-1.0.0"), Phase::Run),
+            Err(VersionitisError::UnknownPackage(_))
+        ));
+
+        // an unresolvable closure is a problem, as it is for resolve
+        let dir = unresolvable();
+        let (outcome, text) = run(|out| graph(out, &dir, Some("foo-1.0.0"), Phase::Run));
+        assert_eq!(outcome, Outcome::Problems);
+        assert!(text.starts_with("unable to resolve foo-1.0.0"), "{}", text);
+    }
+
+    #[test]
+    fn lint_errors_are_problems() {
+        let dir = resources("manifest_repo");
+        let (outcome, _) = run(|out| lint(out, std::slice::from_ref(&dir), &LintConfig::new(), false));
+        assert_eq!(outcome, Outcome::Clean);
+        let mut config = LintConfig::new();
+        config.set("exact-pin", Severity::Deny).unwrap();
+        let (outcome, text) = run(|out| lint(out, std::slice::from_ref(&dir), &config, false));
+        assert_eq!(outcome, Outcome::Problems);
+        assert!(text.contains("deny[exact-pin]"), "{}", text);
+    }
+}
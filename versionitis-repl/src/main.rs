@@ -1,311 +1,240 @@
-use versionitis::PackageRepo;
-use versionitis::traits::TrackPackages;
-use versionitis::errors::VersionitisError;
-use std::fs::File;
-use std::io::{self, Write,};
-use std::path::Path;
-use versionitis::manifest::Manifest;
+//! main.rs
+//!
+//! versiony, a command line interface to versionitis. Each subcommand runs
+//! non-interactively, writing its output to stdout and any error to stderr.
+//!
+//! Exit codes:
+//!
+//! Code | Meaning
+//! --- | ---
+//! 0 | success
+//! 1 | the command ran, but found problems (lint errors, invalid manifests, unresolvable dependencies)
+//! 2 | invalid usage
+//! 3 | an error, such as a file which could not be read or parsed
+//!
+mod commands;
+
+use crate::commands::Outcome;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::{self, Write};
 use std::path::PathBuf;
-use versionitis::manifest_repo::{ManifestArena,ManifestRepo};
-type Feedback = Option<Result<String, String>>;
-
-struct PackageRepoRepl {
-    repo: PackageRepo,
-    feedback:Feedback,
+use std::process::ExitCode;
+use versionitis::{
+    errors::VersionitisError,
+    format::Format,
+    lint::{LintConfig, Severity},
+    manifest::Phase,
+};
+
+/// Manage package repos and manifests
+#[derive(Debug, Parser)]
+#[command(name = "versiony", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-impl PackageRepoRepl {
-    fn new() -> Self {
-        Self {
-            repo: PackageRepo::new(),
-            feedback: None,
-        }
-    }
-
-    fn await_user(&self) {
-        print!("\nPress enter to continue...");
-        io::stdout().flush().unwrap();
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List and add released package versions
+    #[command(subcommand)]
+    Repo(RepoCommand),
+    /// Show and check manifests
+    #[command(subcommand)]
+    Manifest(ManifestCommand),
+    /// Print the resolved dependency closure of a package, one package per line
+    Resolve {
+        /// The manifest directory
+        dir: PathBuf,
+        /// The package to resolve (eg foo-1.0.0)
+        package: String,
+        #[arg(long, value_enum, default_value_t = PhaseArg::Run)]
+        phase: PhaseArg,
+    },
+    /// Print the dependency graph of a manifest directory in graphviz dot format
+    Graph {
+        /// The manifest directory
+        dir: PathBuf,
+        /// Only graph the resolved closure of this package (eg foo-1.0.0)
+        package: Option<String>,
+        #[arg(long, value_enum, default_value_t = PhaseArg::Run)]
+        phase: PhaseArg,
+    },
+    /// Lint manifest files and directories
+    Lint {
+        /// Manifest files or directories
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// A lint config file, mapping rule ids to allow, warn or deny
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Report a rule as an error
+        #[arg(long, value_name = "RULE")]
+        deny: Vec<String>,
+        /// Report a rule as a warning
+        #[arg(long, value_name = "RULE")]
+        warn: Vec<String>,
+        /// Silence a rule
+        #[arg(long, value_name = "RULE")]
+        allow: Vec<String>,
+        /// Print the diagnostics as json
+        #[arg(long)]
+        json: bool,
+    },
+}
 
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            _ => {}
-        }
-    }
+#[derive(Debug, Subcommand)]
+enum RepoCommand {
+    /// List the packages in a repo file, or the versions of one package
+    List {
+        /// The package repo file (yaml, json or toml)
+        file: PathBuf,
+        /// List the versions of this package
+        package: Option<String>,
+    },
+    /// Add package versions to a repo file, creating it if need be
+    Add {
+        /// The package repo file (yaml, json or toml)
+        file: PathBuf,
+        /// The package versions to add (eg foo-1.2.0)
+        #[arg(required = true)]
+        packages: Vec<String>,
+    },
+}
 
-    fn print_cmds(&mut self) {
-        print!("{}[2J", 27 as char);
-        println!(  "\n==================");
-        println!("Versionitis - REPL");
-        println!(  "==================\n");
-        println!("Options:\n");
-        println!("(d) - display repo");
-        println!("(l) - list versions for package");
-        println!("(p) - list packages");
-        println!("(r) - read repo from file");
-        println!("(w) - write repo to file");
-        println!("(v) - add version");
-        println!("(m) - serialize manifest test");
-        println!("(n) - read manifest from disk");
-        println!("(a) - read manifest repo from disk and display");
-        println!("(q) - quit");
+#[derive(Debug, Subcommand)]
+enum ManifestCommand {
+    /// Print a manifest file, or the effective manifest of a package in a directory
+    Show {
+        /// A manifest file, or a manifest directory when a package is supplied
+        path: PathBuf,
+        /// Print the effective manifest of this package (eg foo-1.0.0)
+        package: Option<String>,
+        #[arg(long, value_enum, default_value_t = FormatArg::Yaml)]
+        format: FormatArg,
+    },
+    /// Check that manifest files and directories load and resolve
+    Check {
+        /// Manifest files or directories
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Cross check directories against this package repo file
+        #[arg(long)]
+        packages: Option<PathBuf>,
+    },
+}
 
-        match &self.feedback {
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PhaseArg {
+    Build,
+    Run,
+    Test,
+    Dev,
+}
 
-            Some(Ok(feedback)) => {
-                println!("\n[STATUS] {}", feedback);
-            }
-            Some(Err(feedback)) => {
-                println!("\n[ERROR] {}", feedback);
-            }
-            _ => {}
+impl From<PhaseArg> for Phase {
+    fn from(phase: PhaseArg) -> Self {
+        match phase {
+            PhaseArg::Build => Phase::Build,
+            PhaseArg::Run => Phase::Run,
+            PhaseArg::Test => Phase::Test,
+            PhaseArg::Dev => Phase::Dev,
         }
-
-        // set feedback back to None
-        self.feedback.take();
-        print!("\n(choice):");
-        io::stdout().flush().unwrap();
-    }
-
-    fn display_repo(&self) {
-        print!("{}[2J", 27 as char);
-        println!("{:#?}", self.repo);
-        self.await_user();
     }
+}
 
-    fn add_version(&mut self) -> Result<bool, VersionitisError> {
-        print!("{}[2J", 27 as char);
-        print!("(name-version):");
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                input.pop(); //remove \n
-                let pieces: Vec<&str> = input.split("-").collect();
-                if pieces.len() != 2 {
-                    return Err(
-                        VersionitisError::AddVersionError(
-                            format!("malformed package name: {}", input)
-                        )
-                    );
-                }
-
-                match self.repo.add_version(pieces[0], pieces[1]) {
-                    Ok(_) => {
-                        println!("added {}", input);
-                        self.await_user();
-                    }
-                    Err(e) => {
-                        self.feedback = Some(Err(e.to_string()));
-                        //self.await_user();
-                        return Err(VersionitisError::AddVersionError(e.to_string()));
-                    }
-                };
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Yaml,
+    Json,
+    Toml,
+}
 
-            }
-            Err(e) => {
-                self.feedback = Some(Err(e.to_string()));
-                return Err(VersionitisError::AddVersionError(e.to_string()));
-            }
+impl From<FormatArg> for Format {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Yaml => Format::Yaml,
+            FormatArg::Json => Format::Json,
+            FormatArg::Toml => Format::Toml,
         }
-
-        Ok(true)
     }
+}
 
-    fn list_packages(&self) {
-        print!("{}[2J", 27 as char);
-        println!("========");
-        println!("Packages");
-        println!("========\n");
-        let mut packs = self.repo.packages.iter().map(|(k,_)| k.to_string()).collect::<Vec<String>>();
-        packs.sort();
-
-        for package in &packs {
-            println!("{}", package);
+// build the lint config from a config file, overridden by command line flags
+fn lint_config(
+    config: Option<PathBuf>,
+    deny: &[String],
+    warn: &[String],
+    allow: &[String],
+) -> Result<LintConfig, VersionitisError> {
+    let mut config = match config {
+        Some(path) => versionitis::format::load(path)?,
+        None => LintConfig::new(),
+    };
+    for (rules, severity) in [(allow, Severity::Allow), (warn, Severity::Warn), (deny, Severity::Deny)].iter() {
+        for rule in rules.iter() {
+            config.set(rule, *severity)?;
         }
-        self.await_user();
     }
+    Ok(config)
+}
 
-    fn load_from_file(&mut self) -> Result<(),versionitis::errors::VersionitisError> {
-        print!("{}[2J", 27 as char);
-        let path = std::env::current_dir()?;
-        print!("\n(file [CWD:{}] ):", path.to_str().unwrap());
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                input.pop();
-                if !Path::new(&input).exists() {
-                    self.feedback = Some(Err(format!("path:'{}' does not exist on disk", input)));
-                    return Err(VersionitisError::NonExtantFileError(format!("path:'{}' doesn't exist", input)));
-                }
-                self.feedback = Some(Ok(format!("loaded: {}", input)));
-                let f = std::fs::File::open(input)?;
-                let r: PackageRepo = serde_yaml::from_reader(f)?;
-                self.repo = r;
-                Ok(())
-            }
-            Err(error) => {
-                self.feedback = Some(Err(error.to_string()));
-                Err(VersionitisError::IoError(error.to_string()))
-            }
+fn run(command: Command, out: &mut dyn Write) -> Result<Outcome, VersionitisError> {
+    match command {
+        Command::Repo(RepoCommand::List { file, package }) => commands::repo_list(out, &file, package.as_deref()),
+        Command::Repo(RepoCommand::Add { file, packages }) => commands::repo_add(out, &file, &packages),
+        Command::Manifest(ManifestCommand::Show { path, package, format }) => {
+            commands::manifest_show(out, &path, package.as_deref(), format.into())
         }
-    }
-    fn write_repo(&mut self) -> Result<(), VersionitisError> {
-        print!("{}[2J", 27 as char);
-        print!("\n(output):");
-        io::stdout().flush().unwrap();
-        let mut output = String::new();
-        match io::stdin().read_line(&mut output) {
-            Ok(_) => {
-                output.pop();
-                let f = File::create(output.as_str())?;
-                let r = serde_yaml::to_writer(f, &self.repo)?;
-                self.feedback=Some(Ok(format!("wrote repo to: {}", output)));
-                Ok(r)
-            }
-            Err(e) => {
-                Err(VersionitisError::IoError(e.to_string()))
-            }
+        Command::Manifest(ManifestCommand::Check { paths, packages }) => {
+            commands::manifest_check(out, &paths, packages.as_deref())
+        }
+        Command::Resolve { dir, package, phase } => commands::resolve(out, &dir, &package, phase.into()),
+        Command::Graph { dir, package, phase } => commands::graph(out, &dir, package.as_deref(), phase.into()),
+        Command::Lint { paths, config, deny, warn, allow, json } => {
+            let config = lint_config(config, &deny, &warn, &allow)?;
+            commands::lint(out, &paths, &config, json)
         }
     }
+}
 
-    fn serialize_manifest(&self) -> Result<(),VersionitisError>  {
-        use versionitis::manifest::{Manifest};
-        use versionitis::version_number_interval::VersionNumberInterval;
-        use versionitis::interval::Range::*;
-        type VI=VersionNumberInterval;
-        let mut manifest = Manifest::new("fred-1.0.0");
-        let interval1 = VI::from_range(&Single("0.1.0")).unwrap();
-        let interval2 = VI::from_range(&HalfOpen("0.1.0", "1.0.0")).unwrap();
-        let interval3 = VI::from_range(&Open("0.1.0", "1.0.0")).unwrap();
-        manifest.add_dependency("foo", interval1).unwrap();
-        manifest.add_dependency("bar", interval2).unwrap();
-        manifest.add_dependency("bla", interval3).unwrap();
-
-        let f = File::create("/tmp/manifest.yaml")?;
-        serde_yaml::to_writer(f, &manifest)?;
-        Ok(())
-    }
-
-    fn deserialize_manifest(&mut self) -> Result<Manifest,VersionitisError>  {
-        print!("{}[2J", 27 as char);
-        let path = std::env::current_dir()?;
-        print!("\n(file [CWD:{}] ):", path.to_str().unwrap());
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                input.pop();
-                if !Path::new(&input).exists() {
-                    self.feedback = Some(Err(format!("path:'{}' does not exist on disk", input)));
-                    return Err(VersionitisError::NonExtantFileError(format!("path:'{}' doesn't exist", input)));
-                }
-                self.feedback = Some(Ok(format!("loaded: {}", input)));
-                let f = std::fs::File::open(input)?;
-                let r: Manifest = serde_yaml::from_reader(f)?;
-                Ok(r)
-            }
-
-            Err(error) => {
-                Err(VersionitisError::IoError(error.to_string()))
-            }
+fn main() -> ExitCode {
+    // clap exits with a code of 2 for invalid usage
+    let cli = Cli::parse();
+    let stdout = io::stdout();
+    match run(cli.command, &mut stdout.lock()) {
+        Ok(Outcome::Clean) => ExitCode::SUCCESS,
+        Ok(Outcome::Problems) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(3)
         }
     }
+}
 
-    fn handle_results(&mut self, input: &str) -> Result<bool, VersionitisError> {
-        match input {
-
-            "d" => {
-                self.display_repo();
-            }
-
-            "p" => {
-                self.list_packages();
-            }
-
-            "r" => {
-                println!("r - read repo from file");
-                self.load_from_file()?;
-            }
-
-            "w" => {
-                self.write_repo()?;
-
-            }
-
-            "v" => {
-                self.add_version()?;
-
-            }
-
-            "m" => {
-                match self.serialize_manifest() {
-                    Err(error) => {
-                        self.feedback = Some(Err(error.to_string()));
-                    }
-                    Ok(_) => {}
-                }
-
-            }
-
-            "n" =>  {
-                let result = self.deserialize_manifest();
-                match result {
-                    Ok(manifest) => {
-                        println!("{:#?}", manifest);
-                        self.await_user();
-                    }
-                    Err(error) => {
-                        self.feedback = Some(Err(error.to_string()));
-                    }
-                }
-            }
-            "a" => {
-                let version = env!("CARGO_MANIFEST_DIR");
-                let mut path = PathBuf::from(version);
-                path.push("..");
-                path.push("versionitis-core");
-                path.push("test_resources");
-                path.push("manifest_repo");
-
-                let arena = ManifestArena::new();
-                let repo = ManifestRepo::from_disk(path, &arena).unwrap();
-                println!("{:#?}", repo);
-                self.await_user();
-            }
-            "q" => {
-                println!("quiting");
-                return Ok(true);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
 
-            _ => {self.feedback = Some(Err(format!("invalid choice: '{}'", input)));}
-        }
-        // we return Ok(false) to indicate that we are not quiting
-        Ok(false)
+    #[test]
+    fn cli_is_well_formed() {
+        Cli::command().debug_assert();
     }
 
-    fn run(&mut self) {
-    loop {
-            self.print_cmds();
+    #[test]
+    fn parses_subcommands() {
+        let cli = Cli::try_parse_from(["versiony", "resolve", "manifests", "foo-1.0.0", "--phase", "build"]).unwrap();
+        assert!(matches!(cli.command, Command::Resolve { phase: PhaseArg::Build, .. }));
+        assert!(Cli::try_parse_from(["versiony", "repo", "add", "repo.yaml"]).is_err());
+        assert!(Cli::try_parse_from(["versiony", "lint"]).is_err());
+    }
 
-            let mut input = String::new();
-            match io::stdin().read_line(&mut input) {
-                Ok(_) => {
-                    input.pop();
-                    if let Ok(quitout) = self.handle_results(input.as_str())
-                   { if quitout == true { break }};
-                }
-                Err(error) => self.feedback = Some(Err(error.to_string())),
-            }
-        }
+    #[test]
+    fn lint_flags_override_the_config() {
+        let config = lint_config(None, &["exact-pin".to_string()], &[], &["empty-range".to_string()]).unwrap();
+        assert_eq!(config.severity(versionitis::lint::LintRule::ExactPin), Severity::Deny);
+        assert_eq!(config.severity(versionitis::lint::LintRule::EmptyRange), Severity::Allow);
+        assert!(lint_config(None, &["nope".to_string()], &[], &[]).is_err());
     }
 }
-
-
-
-
-fn main() {
-    let mut repo = PackageRepoRepl::new();
-    repo.run();
-}
\ No newline at end of file